@@ -1,11 +1,17 @@
-use criterion::{criterion_group, criterion_main, Bencher, BenchmarkId, Criterion, Throughput};
+use criterion::measurement::WallTime;
+use criterion::{
+    criterion_group, criterion_main, Bencher, BenchmarkGroup, BenchmarkId, Criterion, Throughput,
+};
 use fingertrees::measure::Size;
-use fingertrees::{rc, ArcRefs, FingerTree, Measured, RcRefs, Refs};
+use fingertrees::monoid::Sum;
+use fingertrees::{rc, ArcRefs, ChunkedFingerTree, FingerTree, Measured, RcRefs, Refs};
 use std::collections::HashMap;
 
 const KB: usize = 1024;
 const SPLIT_1024: &[usize] = &[211, 384, 557, 730, 903];
 
+type Chunked<V> = ChunkedFingerTree<RcRefs, V, 32>;
+
 fn ft_from(c: &mut Criterion) {
     let mut group = c.benchmark_group("from");
     for size in [KB, 2 * KB, 4 * KB, 16 * KB] {
@@ -17,6 +23,9 @@ fn ft_from(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::new("slice", size), &vals, |b, vals| {
             b.iter(|| rc::FingerTree::from(vals.as_slice()))
         });
+        group.bench_with_input(BenchmarkId::new("chunked", size), &vals, |b, vals| {
+            b.iter(|| Chunked::from(vals.as_slice()))
+        });
     }
     group.finish();
 }
@@ -35,6 +44,20 @@ fn ft_split(c: &mut Criterion) {
     group.finish();
 }
 
+fn ft_split_chunked(c: &mut Criterion) {
+    let ft: Chunked<_> = (0..1024).map(Size).collect();
+
+    let mut group = c.benchmark_group("split_chunked");
+    for position in SPLIT_1024 {
+        group.bench_with_input(
+            BenchmarkId::new("position", position),
+            position,
+            |b, position| b.iter(|| ft.split(|m| **m > *position)),
+        );
+    }
+    group.finish();
+}
+
 fn ft_split_left(c: &mut Criterion) {
     let ft: rc::FingerTree<_> = (0..1024).map(Size).collect();
 
@@ -63,6 +86,38 @@ fn ft_split_right(c: &mut Criterion) {
     group.finish();
 }
 
+fn ft_split_many(c: &mut Criterion) {
+    let size = 16 * KB;
+    let ft: rc::FingerTree<_> = (0..size).map(Size).collect();
+
+    let mut group = c.benchmark_group("split_many");
+    for page in [64, 256, 1024] {
+        let bounds: Vec<_> = (page..size).step_by(page).collect();
+        group.bench_with_input(
+            BenchmarkId::new("split_many", page),
+            &bounds,
+            |b, bounds| {
+                b.iter(|| {
+                    ft.split_many(
+                        bounds
+                            .iter()
+                            .map(|bound| move |m: &Sum<usize>| **m > *bound),
+                    )
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("split", page), &bounds, |b, bounds| {
+            b.iter(|| {
+                bounds
+                    .iter()
+                    .map(|bound| ft.split(|m| **m > *bound))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
 fn ft_concat(c: &mut Criterion) {
     let ft: rc::FingerTree<_> = (0..1024).map(Size).collect();
     let ft_split: HashMap<_, _> = SPLIT_1024
@@ -81,6 +136,31 @@ fn ft_concat(c: &mut Criterion) {
     group.finish();
 }
 
+fn ft_concat_all(c: &mut Criterion) {
+    let ft: rc::FingerTree<_> = (0..16 * KB).map(Size).collect();
+
+    let mut group = c.benchmark_group("concat_all");
+    for count in [16, 64, 256] {
+        let page = 16 * KB / count;
+        let trees = ft.split_many(
+            (page..16 * KB)
+                .step_by(page)
+                .map(|bound| move |m: &Sum<usize>| **m > bound),
+        );
+        group.bench_with_input(BenchmarkId::new("concat_all", count), &trees, |b, trees| {
+            b.iter(|| rc::FingerTree::concat_all(trees.iter().cloned()))
+        });
+        group.bench_with_input(BenchmarkId::new("fold", count), &trees, |b, trees| {
+            b.iter(|| {
+                trees
+                    .iter()
+                    .fold(rc::FingerTree::new(), |acc, tree| &acc + tree)
+            })
+        });
+    }
+    group.finish();
+}
+
 /// Iterator based destructuring FingerTree with `view`
 struct ViewIter<R, V>
 where
@@ -124,6 +204,88 @@ fn ft_iter(c: &mut Criterion) {
     group.bench_with_input(BenchmarkId::new("view", len), &ft, |b, ft| {
         b.iter(|| ViewIter::new(ft).count())
     });
+    group.bench_with_input(BenchmarkId::new("fold", len), &ft, |b, ft| {
+        b.iter(|| ft.iter().fold(0, |sum, value| sum + *value))
+    });
+    group.bench_with_input(BenchmarkId::new("try_fold", len), &ft, |b, ft| {
+        b.iter(|| {
+            ft.iter()
+                .try_fold(0usize, |sum, value| sum.checked_add(*value))
+        })
+    });
+    group.bench_with_input(BenchmarkId::new("for_each", len), &ft, |b, ft| {
+        b.iter(|| {
+            let mut sum = 0;
+            ft.for_each(|value| sum += **value);
+            sum
+        })
+    });
+    let chunked: Chunked<_> = (0..len).map(Size).collect();
+    group.bench_with_input(BenchmarkId::new("chunked", len), &chunked, |b, ft| {
+        b.iter(|| ft.iter().fold(0, |sum, value| sum + **value))
+    });
+    group.bench_with_input(
+        BenchmarkId::new("chunked_for_each", len),
+        &chunked,
+        |b, ft| {
+            b.iter(|| {
+                let mut sum = 0;
+                ft.for_each(|value| sum += **value);
+                sum
+            })
+        },
+    );
+    let vec: Vec<_> = (0..len).map(Size).collect();
+    group.bench_with_input(BenchmarkId::new("vec", len), &vec, |b, vec| {
+        b.iter(|| vec.iter().fold(0, |sum, value| sum + **value))
+    });
+    group.finish();
+}
+
+fn ft_wide(c: &mut Criterion) {
+    // wider nodes and digits make the tree shallower, compare them with the default branching
+    fn bench_branching<R>(group: &mut BenchmarkGroup<WallTime>, len: usize, name: &str)
+    where
+        R: Refs<Size<usize>>,
+    {
+        let ft: FingerTree<R, _> = (0..len).map(Size).collect();
+        group.bench_with_input(BenchmarkId::new("split", name), &ft, |b, ft| {
+            b.iter(|| ft.split(|m| **m > len / 3))
+        });
+        group.bench_with_input(BenchmarkId::new("find", name), &ft, |b, ft| {
+            b.iter(|| ft.find(|m| **m > len / 3).cloned())
+        });
+        group.bench_with_input(BenchmarkId::new("iter", name), &ft, |b, ft| {
+            b.iter(|| ft.iter().fold(0, |sum, value| sum + *value))
+        });
+    }
+
+    let len = 65536;
+    let mut group = c.benchmark_group("wide");
+    bench_branching::<RcRefs>(&mut group, len, "node3");
+    bench_branching::<RcRefs<8, 9>>(&mut group, len, "node8");
+    bench_branching::<RcRefs<16, 17>>(&mut group, len, "node16");
+    bench_branching::<RcRefs<32, 33>>(&mut group, len, "node32");
+    group.finish();
+}
+
+fn ft_small(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small");
+    for size in [1, 2, 4, 7] {
+        group.bench_with_input(BenchmarkId::new("push", size), &size, |b, size| {
+            b.iter(|| (0..*size).map(Size).collect::<rc::FingerTree<_>>())
+        });
+        let ft: rc::FingerTree<_> = (0..size).map(Size).collect();
+        group.bench_with_input(BenchmarkId::new("iter", size), &ft, |b, ft| {
+            b.iter(|| ft.iter().fold(0, |sum, value| sum + *value))
+        });
+        group.bench_with_input(BenchmarkId::new("view", size), &ft, |b, ft| {
+            b.iter(|| ViewIter::new(ft).fold(0, |sum, value| sum + *value))
+        });
+        group.bench_with_input(BenchmarkId::new("vec", size), &size, |b, size| {
+            b.iter(|| (0..*size).map(Size).collect::<Vec<_>>())
+        });
+    }
     group.finish();
 }
 
@@ -158,11 +320,16 @@ criterion_group! {
     benches,
     ft_arc_vs_rc,
     ft_concat,
+    ft_concat_all,
     ft_from,
     ft_iter,
+    ft_small,
     ft_split,
+    ft_split_chunked,
     ft_split_left,
+    ft_split_many,
     ft_split_right,
+    ft_wide,
 }
 
 criterion_main!(benches);
@@ -0,0 +1,329 @@
+//! [`Action`](action/trait.Action.html) trait used by lazy updates, and storage of
+//! pending actions inside of a tree
+use std::any::Any;
+use std::cell::{Cell, OnceCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use crate::measure::Measured;
+use crate::monoid::Monoid;
+
+/// Monoid action on measured values
+///
+/// Action transforms both values and their measures, which allows finger tree
+/// to apply it to a whole subtree in constant time by only updating cached
+/// measure of the subtree and postponing update of its children until they are
+/// accessed (lazy propagation).
+///
+/// Every action implementation should satisfy following laws:
+///  - **composition**: `a.join(b)` is an action which applies `a` first and then `b`,
+///    that is `a.join(b).act(v) == b.act(a.act(v))`
+///  - **identity**: `unit().act(v) == v`
+///  - **consistency**: `a.act_measure(v.measure()) == a.act(v).measure()`
+///  - **distributivity**: `a.act_measure(m0.join(m1)) == a.act_measure(m0).join(a.act_measure(m1))`
+///
+/// Pending actions are stored inside of the tree with their type erased, so they
+/// must be `'static`, and trees based on thread safe references also require them
+/// to be `Send + Sync`, see [`ActionRef`](trait.ActionRef.html).
+pub trait Action<V>: Monoid + Clone
+where
+    V: Measured,
+{
+    /// Apply action to a value
+    fn act(&self, value: &V) -> V;
+
+    /// Apply action to an accumulated measure of values
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure;
+}
+
+/// Type erased [`Action`](trait.Action.html) stored by single threaded references
+pub trait LocalAction<V>
+where
+    V: Measured,
+{
+    /// Apply action to a value
+    fn act(&self, value: &V) -> V;
+
+    /// Apply action to an accumulated measure of values
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure;
+
+    /// Action which applies `before` first and then this action, only available
+    /// if both actions have the same type
+    fn compose(&self, before: &dyn LocalAction<V>) -> Option<Rc<dyn LocalAction<V>>>;
+
+    /// Action as `Any`, used to recover type of the action in `compose`
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<V, A> LocalAction<V> for A
+where
+    V: Measured,
+    A: Action<V> + 'static,
+{
+    fn act(&self, value: &V) -> V {
+        Action::act(self, value)
+    }
+
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure {
+        Action::act_measure(self, measure)
+    }
+
+    fn compose(&self, before: &dyn LocalAction<V>) -> Option<Rc<dyn LocalAction<V>>> {
+        let before = before.as_any().downcast_ref::<A>()?;
+        Some(Rc::new(before.join(self)))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Type erased [`Action`](trait.Action.html) stored by thread safe references
+pub trait SyncAction<V>: Send + Sync
+where
+    V: Measured,
+{
+    /// Apply action to a value
+    fn act(&self, value: &V) -> V;
+
+    /// Apply action to an accumulated measure of values
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure;
+
+    /// Action which applies `before` first and then this action, only available
+    /// if both actions have the same type
+    fn compose(&self, before: &dyn SyncAction<V>) -> Option<Arc<dyn SyncAction<V>>>;
+
+    /// Action as `Any`, used to recover type of the action in `compose`
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<V, A> SyncAction<V> for A
+where
+    V: Measured,
+    A: Action<V> + Send + Sync + 'static,
+{
+    fn act(&self, value: &V) -> V {
+        Action::act(self, value)
+    }
+
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure {
+        Action::act_measure(self, measure)
+    }
+
+    fn compose(&self, before: &dyn SyncAction<V>) -> Option<Arc<dyn SyncAction<V>>> {
+        let before = before.as_any().downcast_ref::<A>()?;
+        Some(Arc::new(before.join(self)))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Reference on a type erased action used by single threaded references
+pub type LocalActionRef<V> = Rc<dyn LocalAction<V>>;
+
+/// Reference on a type erased action used by thread safe references
+pub type SyncActionRef<V> = Arc<dyn SyncAction<V>>;
+
+/// Reference on a pending action stored in lazy nodes and spines of a tree
+///
+/// It is selected by [`Refs::Action`](../trait.Refs.html#associatedtype.Action),
+/// single threaded references use [`LocalActionRef`](type.LocalActionRef.html)
+/// which accepts any `'static` action, and thread safe references use
+/// [`SyncActionRef`](type.SyncActionRef.html) which also requires action to be
+/// `Send + Sync`.
+pub trait ActionRef<V>: Clone
+where
+    V: Measured,
+{
+    /// Cell holding pending action together with a value it is not yet pushed into
+    type Cell<T>: LazyCell<T, Self>;
+
+    /// Apply action to a value
+    fn act(&self, value: &V) -> V;
+
+    /// Apply action to an accumulated measure of values
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure;
+
+    /// Action which applies `before` first and then this action, if they can be combined
+    fn compose(&self, before: &Self) -> Option<Self>;
+}
+
+impl<V> ActionRef<V> for LocalActionRef<V>
+where
+    V: Measured,
+{
+    type Cell<T> = LocalCell<T, Self>;
+
+    fn act(&self, value: &V) -> V {
+        (**self).act(value)
+    }
+
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure {
+        (**self).act_measure(measure)
+    }
+
+    fn compose(&self, before: &Self) -> Option<Self> {
+        (**self).compose(&**before)
+    }
+}
+
+impl<V> ActionRef<V> for SyncActionRef<V>
+where
+    V: Measured,
+{
+    type Cell<T> = SyncCell<T, Self>;
+
+    fn act(&self, value: &V) -> V {
+        (**self).act(value)
+    }
+
+    fn act_measure(&self, measure: &V::Measure) -> V::Measure {
+        (**self).act_measure(measure)
+    }
+
+    fn compose(&self, before: &Self) -> Option<Self> {
+        (**self).compose(&**before)
+    }
+}
+
+/// Action reference which can be constructed from action `A`
+pub trait FromAction<V, A>: ActionRef<V>
+where
+    V: Measured,
+{
+    /// Erase type of the `action`
+    fn from_action(action: A) -> Self;
+}
+
+impl<V, A> FromAction<V, A> for LocalActionRef<V>
+where
+    V: Measured,
+    A: Action<V> + 'static,
+{
+    fn from_action(action: A) -> Self {
+        Rc::new(action)
+    }
+}
+
+impl<V, A> FromAction<V, A> for SyncActionRef<V>
+where
+    V: Measured,
+    A: Action<V> + Send + Sync + 'static,
+{
+    fn from_action(action: A) -> Self {
+        Arc::new(action)
+    }
+}
+
+/// Value which can be forced by pushing pending action one level down
+#[doc(hidden)]
+pub trait PushDown<A>: Sized {
+    /// Value with `action` applied to it, with action pushed down to its children
+    fn push_down(&self, action: &A) -> Self;
+}
+
+/// Cell holding pending action together with a value until the value is forced,
+/// after that only forced value is kept
+#[doc(hidden)]
+pub trait LazyCell<T, A>
+where
+    A: Clone,
+{
+    /// Create cell with `action` pending on the `value`
+    fn new(action: A, value: T) -> Self;
+
+    /// Forced value, pending action is pushed down on the first call
+    fn force(&self) -> &T
+    where
+        T: PushDown<A>;
+
+    /// Pending action and the value it is pending on, if the cell is not forced yet
+    fn pending(&self) -> Option<(A, T)>
+    where
+        T: Clone;
+}
+
+/// Lazy cell used by single threaded references
+#[doc(hidden)]
+pub struct LocalCell<T, A> {
+    pending: Cell<Option<(A, T)>>,
+    forced: OnceCell<T>,
+}
+
+impl<T, A> LazyCell<T, A> for LocalCell<T, A>
+where
+    A: Clone,
+{
+    fn new(action: A, value: T) -> Self {
+        LocalCell {
+            pending: Cell::new(Some((action, value))),
+            forced: OnceCell::new(),
+        }
+    }
+
+    fn force(&self) -> &T
+    where
+        T: PushDown<A>,
+    {
+        self.forced.get_or_init(|| {
+            let (action, value) = self.pending.take().expect("lazy cell lost its value");
+            value.push_down(&action)
+        })
+    }
+
+    fn pending(&self) -> Option<(A, T)>
+    where
+        T: Clone,
+    {
+        let pending = self.pending.take();
+        let result = pending.clone();
+        self.pending.set(pending);
+        result
+    }
+}
+
+/// Lazy cell used by thread safe references
+#[doc(hidden)]
+pub struct SyncCell<T, A> {
+    pending: Mutex<Option<(A, T)>>,
+    forced: OnceLock<T>,
+}
+
+impl<T, A> LazyCell<T, A> for SyncCell<T, A>
+where
+    A: Clone,
+{
+    fn new(action: A, value: T) -> Self {
+        SyncCell {
+            pending: Mutex::new(Some((action, value))),
+            forced: OnceLock::new(),
+        }
+    }
+
+    fn force(&self) -> &T
+    where
+        T: PushDown<A>,
+    {
+        self.forced.get_or_init(|| {
+            let pending = self
+                .pending
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            let (action, value) = pending.expect("lazy cell lost its value");
+            value.push_down(&action)
+        })
+    }
+
+    fn pending(&self) -> Option<(A, T)>
+    where
+        T: Clone,
+    {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
@@ -0,0 +1,484 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::measure::Measured;
+use crate::monoid::Monoid;
+use crate::node::{Node, NodeView};
+use crate::reference::{ArcRefs, RcRefs, Refs};
+use crate::tree::Tree;
+use crate::{infallible, FingerTree};
+
+/// References type family which also defines storage of values of a
+/// [`Chunk`](struct.Chunk.html)
+///
+/// Implemented for [`RcRefs`](enum.RcRefs.html) with `Rc<[V]>` storage and for
+/// [`ArcRefs`](enum.ArcRefs.html) with `Arc<[V]>` storage, so chunks use the same kind
+/// of reference counting as the rest of the tree.
+pub trait ChunkRefs<V> {
+    /// Reference counted slice of values
+    type Values: Clone + Deref<Target = [V]> + FromIterator<V>;
+}
+
+impl<V, const N: usize, const D: usize> ChunkRefs<V> for RcRefs<N, D> {
+    type Values = Rc<[V]>;
+}
+
+impl<V, const N: usize, const D: usize> ChunkRefs<V> for ArcRefs<N, D> {
+    type Values = Arc<[V]>;
+}
+
+/// Chunk of consecutive values stored in a single leaf of
+/// [`ChunkedFingerTree`](struct.ChunkedFingerTree.html)
+///
+/// Chunk is a window into a reference counted slice of values, so cloning it does not
+/// copy values, and chunks produced by splitting or viewing a chunk share storage
+/// with it.
+pub struct Chunk<R, V>
+where
+    R: ChunkRefs<V>,
+    V: Measured,
+{
+    values: R::Values,
+    start: u32,
+    end: u32,
+    measure: V::Measure,
+}
+
+impl<R, V> Chunk<R, V>
+where
+    R: ChunkRefs<V>,
+    V: Measured,
+{
+    fn new(values: R::Values) -> Self {
+        let measure = (&*values).measure();
+        Self::with_measure(values, measure)
+    }
+
+    fn with_measure(values: R::Values, measure: V::Measure) -> Self {
+        Chunk {
+            start: 0,
+            end: values.len() as u32,
+            values,
+            measure,
+        }
+    }
+
+    /// Chunk with values from `start` to `end` of this chunk, sharing its storage
+    fn slice(&self, start: usize, end: usize) -> Self {
+        let values = &self.as_slice()[start..end];
+        Chunk {
+            values: self.values.clone(),
+            start: self.start + start as u32,
+            end: self.start + end as u32,
+            measure: values.measure(),
+        }
+    }
+
+    /// Values of the chunk
+    pub fn as_slice(&self) -> &[V] {
+        &self.values[self.start as usize..self.end as usize]
+    }
+}
+
+impl<R, V> Clone for Chunk<R, V>
+where
+    R: ChunkRefs<V>,
+    V: Measured,
+{
+    fn clone(&self) -> Self {
+        Chunk {
+            values: self.values.clone(),
+            start: self.start,
+            end: self.end,
+            measure: self.measure.clone(),
+        }
+    }
+}
+
+impl<R, V> Measured for Chunk<R, V>
+where
+    R: ChunkRefs<V>,
+    V: Measured,
+{
+    type Measure = V::Measure;
+
+    fn measure(&self) -> Self::Measure {
+        self.measure.clone()
+    }
+
+    fn count(measure: &Self::Measure) -> Option<usize> {
+        V::count(measure)
+    }
+}
+
+impl<R, V> Deref for Chunk<R, V>
+where
+    R: ChunkRefs<V>,
+    V: Measured,
+{
+    type Target = [V];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+/// Finger tree which stores up to `N` values in each leaf
+///
+/// Storing each value in its own leaf costs an allocation and a pointer dereference per
+/// value, which dominates for small values such as `u32` or `char`. This tree groups
+/// consecutive values in [`Chunk`](struct.Chunk.html)s with cached accumulated measure,
+/// `split` and `find` descend into the chunk once the tree search reaches it.
+/// `N` is the arity of the bottom level of the tree, referred to as `CHUNK` in
+/// complexity of operations. Wider chunks make the tree shallower and scans more cache
+/// friendly, at the cost of copying up to `N` values when a value is pushed into a chunk
+/// or adjacent chunks are merged, while splitting and viewing share storage of the chunk.
+/// Chunks are reference counted in the same way as nodes of the tree, see
+/// [`ChunkRefs`](trait.ChunkRefs.html). Underlying finger tree of chunks is available
+/// with [`as_tree`](struct.ChunkedFingerTree.html#method.as_tree).
+///
+/// # Example:
+/// ```
+/// use fingertrees::measure::Size;
+/// use fingertrees::monoid::Sum;
+/// use fingertrees::{ChunkedFingerTree, Measured, RcRefs};
+///
+/// let ft: ChunkedFingerTree<RcRefs, _, 32> = (0..1000u32).map(Size).collect();
+/// let (left, right) = ft.split(|m| *m > Sum(100));
+/// assert_eq!(left.measure(), Sum(100));
+/// assert_eq!(right.iter().next(), Some(&Size(100)));
+/// ```
+pub struct ChunkedFingerTree<R, V, const N: usize>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    tree: FingerTree<R, Chunk<R, V>>,
+}
+
+impl<R, V, const N: usize> ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    const CHUNK_SIZE: usize = {
+        assert!(N > 0, "chunk size must be positive");
+        assert!(N <= u32::MAX as usize, "chunk size must fit into u32");
+        N
+    };
+
+    /// Constructs a new, empty `ChunkedFingerTree`
+    ///
+    /// Complexity: `O(1)`
+    pub fn new() -> Self {
+        ChunkedFingerTree {
+            tree: FingerTree::new(),
+        }
+    }
+
+    fn from_tree(tree: FingerTree<R, Chunk<R, V>>) -> Self {
+        ChunkedFingerTree { tree }
+    }
+
+    fn from_tree_rec(tree: Tree<R, Chunk<R, V>>) -> Self {
+        ChunkedFingerTree {
+            tree: FingerTree::from_rec(tree),
+        }
+    }
+
+    /// Underlying finger tree of chunks
+    pub fn as_tree(&self) -> &FingerTree<R, Chunk<R, V>> {
+        &self.tree
+    }
+
+    /// Returns `true` if tree is empty
+    ///
+    /// Complexity: `O(1)`
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Creates new tree with value prepended to the front of the current tree
+    ///
+    /// Values of the first chunk are copied into a new chunk together with `value`,
+    /// unless the chunk is full.
+    ///
+    /// Complexity: `O(CHUNK)`, plus amortized `O(1)` of the underlying tree
+    pub fn push_left(&self, value: V) -> Self {
+        match self.tree.view_left() {
+            Some((head, tail)) if head.len() < Self::CHUNK_SIZE => {
+                let measure = value.measure().join(&head.measure);
+                let values = Some(value).into_iter().chain(head.iter().cloned());
+                Self::from_tree(tail.push_left(Chunk::with_measure(values.collect(), measure)))
+            }
+            _ => Self::from_tree(
+                self.tree
+                    .push_left(Chunk::new(Some(value).into_iter().collect())),
+            ),
+        }
+    }
+
+    /// Creates new tree with value appended to the end of the current tree
+    ///
+    /// Values of the last chunk are copied into a new chunk together with `value`,
+    /// unless the chunk is full.
+    ///
+    /// Complexity: `O(CHUNK)`, plus amortized `O(1)` of the underlying tree
+    pub fn push_right(&self, value: V) -> Self {
+        match self.tree.view_right() {
+            Some((last, init)) if last.len() < Self::CHUNK_SIZE => {
+                let measure = last.measure.join(&value.measure());
+                let values = last.iter().cloned().chain(Some(value));
+                Self::from_tree(init.push_right(Chunk::with_measure(values.collect(), measure)))
+            }
+            _ => Self::from_tree(
+                self.tree
+                    .push_right(Chunk::new(Some(value).into_iter().collect())),
+            ),
+        }
+    }
+
+    /// Destructure tree into a tuple with first element and the rest of the tree
+    ///
+    /// Rest of the first chunk shares its storage, but its measure is recomputed.
+    ///
+    /// Complexity: `O(CHUNK)`, plus amortized `O(1)` of the underlying tree
+    pub fn view_left(&self) -> Option<(V, Self)> {
+        let (head, tail) = self.tree.view_left()?;
+        let value = head.first()?.clone();
+        if head.len() == 1 {
+            Some((value, Self::from_tree(tail)))
+        } else {
+            let tail = tail.push_left(head.slice(1, head.len()));
+            Some((value, Self::from_tree(tail)))
+        }
+    }
+
+    /// Destructure tree into a tuple with last element and the rest of the tree
+    ///
+    /// Rest of the last chunk shares its storage, but its measure is recomputed.
+    ///
+    /// Complexity: `O(CHUNK)`, plus amortized `O(1)` of the underlying tree
+    pub fn view_right(&self) -> Option<(V, Self)> {
+        let (last, init) = self.tree.view_right()?;
+        let value = last.last()?.clone();
+        if last.len() == 1 {
+            Some((value, Self::from_tree(init)))
+        } else {
+            let init = init.push_right(last.slice(0, last.len() - 1));
+            Some((value, Self::from_tree(init)))
+        }
+    }
+
+    /// Destructure tree into two trees, using provided predicate
+    ///
+    /// Works the same way as [`FingerTree::split`](struct.FingerTree.html#method.split),
+    /// predicate is called with accumulated measure of chunks while searching the tree,
+    /// and with accumulated measure of values while searching inside of the chunk.
+    ///
+    /// Complexity: `O(ln(N / CHUNK) + CHUNK)`
+    pub fn split<F>(&self, mut pred: F) -> (Self, Self)
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        if self.is_empty() {
+            return (Self::new(), Self::new());
+        } else if !pred(&self.tree.measure()) {
+            return (self.clone(), Self::new());
+        }
+        let (left, chunk, right) = infallible(
+            self.tree
+                .rec()
+                .split(V::Measure::unit(), &mut |measure| Ok(pred(measure))),
+        );
+        let values = match chunk.view() {
+            NodeView::Leaf(values) => values,
+            _ => unreachable!("not leaf returned from top level of the finger-tree"),
+        };
+        let index = split_index(left.measure(), values, &mut pred);
+        if index == 0 {
+            // chunk is not split, so its leaf is reused as is
+            let right = right.push_left(chunk);
+            return (Self::from_tree_rec(left), Self::from_tree_rec(right));
+        }
+        let left = left.push_right(Node::leaf(values.slice(0, index)));
+        let right = right.push_left(Node::leaf(values.slice(index, values.len())));
+        (Self::from_tree_rec(left), Self::from_tree_rec(right))
+    }
+
+    /// partial logic from `.split(...)` with only **left** part returned
+    pub fn split_left<F>(&self, pred: F) -> Self
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        self.split(pred).0
+    }
+
+    /// partial logic from `.split(...)` with only **right** part returned
+    pub fn split_right<F>(&self, pred: F) -> Self
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        self.split(pred).1
+    }
+
+    /// Find element for which predicate function `pred` flips from `false` to `true`
+    ///
+    /// Complexity: `O(ln(N / CHUNK) + CHUNK)`
+    pub fn find<F>(&self, mut pred: F) -> Option<&V>
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        if self.is_empty() || !pred(&self.tree.measure()) {
+            return None;
+        }
+        let (measure, chunk) = infallible(self.tree.find_prefix(&mut |measure| Ok(pred(measure))));
+        let index = split_index(measure, chunk, &mut pred);
+        chunk.get(index)
+    }
+
+    /// Construct new tree which is concatenation of `self` and `other`, adjacent chunks
+    /// are merged if they fit into a single chunk
+    ///
+    /// Complexity: `O(ln(N) + CHUNK)`
+    pub fn concat(&self, other: &Self) -> Self {
+        if let (Some((last, init)), Some((head, tail))) =
+            (self.tree.view_right(), other.tree.view_left())
+        {
+            if last.len() + head.len() <= Self::CHUNK_SIZE {
+                let measure = last.measure.join(&head.measure);
+                let values = last.iter().chain(head.iter()).cloned().collect();
+                let init = init.push_right(Chunk::with_measure(values, measure));
+                return Self::from_tree(init.concat(&tail));
+            }
+        }
+        Self::from_tree(self.tree.concat(&other.tree))
+    }
+
+    /// Iterator over references to all elements of the tree
+    pub fn iter(&self) -> impl Iterator<Item = &V> {
+        self.tree
+            .leaf_runs()
+            .flatten()
+            .flat_map(|chunk| chunk.as_slice())
+    }
+
+    /// Call `f` with a reference to every element of the tree from left to right
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&V),
+    {
+        self.tree.for_each(|chunk| chunk.iter().for_each(&mut f))
+    }
+}
+
+/// Index of the value in the chunk for which predicate flips, `measure` is accumulated
+/// measure of all values before the chunk
+fn split_index<V, F>(mut measure: V::Measure, values: &[V], pred: &mut F) -> usize
+where
+    V: Measured,
+    F: FnMut(&V::Measure) -> bool,
+{
+    let last = values.len() - 1;
+    for (index, value) in values[..last].iter().enumerate() {
+        measure = measure.join(&value.measure());
+        if pred(&measure) {
+            return index;
+        }
+    }
+    last
+}
+
+impl<R, V, const N: usize> Clone for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    fn clone(&self) -> Self {
+        Self::from_tree(self.tree.clone())
+    }
+}
+
+impl<R, V, const N: usize> Default for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, V, const N: usize> Measured for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    type Measure = V::Measure;
+
+    fn measure(&self) -> Self::Measure {
+        self.tree.measure()
+    }
+}
+
+impl<R, V, const N: usize> PartialEq for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<R, V, const N: usize> Eq for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured + Eq,
+{
+}
+
+impl<R, V, const N: usize> FromIterator<V> for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let chunks = std::iter::from_fn(|| {
+            let values: R::Values = iter.by_ref().take(Self::CHUNK_SIZE).collect();
+            if values.is_empty() {
+                None
+            } else {
+                Some(Chunk::new(values))
+            }
+        });
+        Self::from_tree(chunks.collect())
+    }
+}
+
+impl<'a, R, V, const N: usize> From<&'a [V]> for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured,
+{
+    fn from(slice: &'a [V]) -> Self {
+        let chunks = slice.chunks(Self::CHUNK_SIZE);
+        let chunks = chunks.map(|values| Chunk::new(values.iter().cloned().collect()));
+        Self::from_tree(chunks.collect())
+    }
+}
+
+impl<R, V, const N: usize> fmt::Debug for ChunkedFingerTree<R, V, N>
+where
+    R: Refs<Chunk<R, V>> + ChunkRefs<V>,
+    V: Measured + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ChunkedFingerTree")?;
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -1,83 +1,237 @@
+use std::convert::Infallible;
+use std::marker::PhantomData;
 use std::ops::Add;
 
+use arrayvec::ArrayVec;
+
 use crate::measure::Measured;
 use crate::monoid::Monoid;
-use crate::node::{Node, NodeInner};
+use crate::node::{Node, NodeView};
 use crate::reference::Refs;
 
+/// Digit of a tree, which stores nodes of digits wider than four nodes inline in
+/// storage selected by [`Refs::Capacity`](trait.Refs.html#associatedtype.Capacity)
+pub(crate) type NodeDigit<R, V> =
+    Digit<Node<R, V>, <<R as Refs<V>>::Capacity as WideDigits>::Nodes<Node<R, V>>>;
+
 #[derive(Clone)]
-pub enum Digit<V> {
+pub enum Digit<V, W> {
     One([V; 1]),
     Two([V; 2]),
     Three([V; 3]),
     Four([V; 4]),
+    /// Digit of more than four nodes, only used by trees with digit capacity above four
+    Many(W),
 }
 
-impl<V> Digit<V>
+impl<V, W> Digit<V, W>
 where
-    V: Measured,
+    W: AsRef<[V]>,
 {
-    pub(crate) fn split<F>(&self, mut measure: V::Measure, pred: &mut F) -> (&[V], &V, &[V])
+    pub(crate) fn map<U, X, F>(&self, mut f: F) -> Digit<U, X>
     where
-        F: FnMut(&V::Measure) -> bool,
+        X: FromIterator<U>,
+        F: FnMut(&V) -> U,
     {
-        let slice = self.as_ref();
-        if slice.len() == 1 {
-            (&[], &slice[0], &[])
-        } else {
-            let slice = self.as_ref();
-            for (index, item) in slice.iter().enumerate() {
-                measure = measure.join(&item.measure());
-                if pred(&measure) {
-                    return (&slice[..index], &slice[index], &slice[index + 1..]);
-                }
-            }
-            let index = slice.len() - 1;
-            (&slice[..index], &slice[index], &[])
+        match self {
+            Digit::One([v0]) => Digit::One([f(v0)]),
+            Digit::Two([v0, v1]) => Digit::Two([f(v0), f(v1)]),
+            Digit::Three([v0, v1, v2]) => Digit::Three([f(v0), f(v1), f(v2)]),
+            Digit::Four([v0, v1, v2, v3]) => Digit::Four([f(v0), f(v1), f(v2), f(v3)]),
+            Digit::Many(vs) => Digit::Many(vs.as_ref().iter().map(f).collect()),
         }
     }
+}
+
+impl<V, W> Digit<V, W>
+where
+    V: Clone,
+    W: FromIterator<V>,
+{
+    /// Digit made of `left` values followed by `right` values
+    pub(crate) fn concat(left: &[V], right: &[V]) -> Self {
+        match (left, right) {
+            (_, []) => Digit::from(left),
+            ([], _) => Digit::from(right),
+            ([v0], [v1]) => Digit::Two([v0.clone(), v1.clone()]),
+            ([v0], [v1, v2]) => Digit::Three([v0.clone(), v1.clone(), v2.clone()]),
+            ([v0], [v1, v2, v3]) => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
+            ([v0, v1], [v2]) => Digit::Three([v0.clone(), v1.clone(), v2.clone()]),
+            ([v0, v1], [v2, v3]) => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
+            ([v0, v1, v2], [v3]) => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
+            _ => Digit::many(left, right),
+        }
+    }
+
+    /// Digit of more than four values, kept out of line so building digits of
+    /// default capacity stays cheap
+    #[cold]
+    fn many(left: &[V], right: &[V]) -> Self {
+        Digit::Many(left.iter().chain(right).cloned().collect())
+    }
+}
+
+impl<V, W> Digit<V, W>
+where
+    V: Measured,
+    W: AsRef<[V]>,
+{
+    pub(crate) fn split<E, F>(
+        &self,
+        measure: V::Measure,
+        pred: &mut F,
+    ) -> Result<(&[V], &V, &[V]), E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
+    {
+        split(self.as_ref(), measure, pred)
+    }
+
+    pub(crate) fn find<E, F>(
+        &self,
+        measure: V::Measure,
+        pred: &mut F,
+    ) -> Result<(V::Measure, &V), E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
+    {
+        find(self.as_ref(), measure, pred)
+    }
 
-    pub(crate) fn find<F>(&self, mut measure: V::Measure, pred: &mut F) -> (V::Measure, &V)
+    /// Find element for which predicate flips, returning it together with measures
+    /// of elements on its left and right sides (including outer `left` and `right`)
+    pub(crate) fn search<F>(
+        &self,
+        left: V::Measure,
+        right: V::Measure,
+        pred: &mut F,
+    ) -> (V::Measure, V::Measure, &V)
     where
-        F: FnMut(&V::Measure) -> bool,
+        F: FnMut(&V::Measure, &V::Measure) -> bool,
     {
-        let last = self.as_ref().len() - 1;
-        for (index, value) in self.as_ref().iter().enumerate() {
-            let next_measure = measure.join(&value.measure());
-            if pred(&next_measure) || index == last {
-                return (measure, value);
+        search(self.as_ref(), left, right, pred)
+    }
+}
+
+/// Split non empty `slice` on the element for which predicate flips, `measure` is
+/// accumulated measure of all elements before the slice
+fn split<'a, V, E, F>(
+    slice: &'a [V],
+    mut measure: V::Measure,
+    pred: &mut F,
+) -> Result<(&'a [V], &'a V, &'a [V]), E>
+where
+    V: Measured,
+    F: FnMut(&V::Measure) -> Result<bool, E>,
+{
+    if slice.len() == 1 {
+        Ok((&[], &slice[0], &[]))
+    } else {
+        for (index, item) in slice.iter().enumerate() {
+            measure = measure.join(&item.measure());
+            if pred(&measure)? {
+                return Ok((&slice[..index], &slice[index], &slice[index + 1..]));
             }
-            measure = next_measure;
         }
-        unreachable!()
+        let index = slice.len() - 1;
+        Ok((&slice[..index], &slice[index], &[]))
     }
 }
 
-impl<'a, V, R> Add<R> for &'a Digit<V>
+/// Find element of non empty `slice` for which predicate flips, returning it together
+/// with accumulated measure of all elements before it
+fn find<'a, V, E, F>(
+    slice: &'a [V],
+    mut measure: V::Measure,
+    pred: &mut F,
+) -> Result<(V::Measure, &'a V), E>
+where
+    V: Measured,
+    F: FnMut(&V::Measure) -> Result<bool, E>,
+{
+    let last = slice.len() - 1;
+    for (index, value) in slice.iter().enumerate() {
+        let next_measure = measure.join(&value.measure());
+        if pred(&next_measure)? || index == last {
+            return Ok((measure, value));
+        }
+        measure = next_measure;
+    }
+    unreachable!()
+}
+
+/// Find element of non empty `slice` for which predicate flips, returning it together
+/// with measures of elements on its left and right sides (including outer `left` and `right`)
+pub(crate) fn search<'a, V, F>(
+    slice: &'a [V],
+    left: V::Measure,
+    right: V::Measure,
+    pred: &mut F,
+) -> (V::Measure, V::Measure, &'a V)
+where
+    V: Measured,
+    F: FnMut(&V::Measure, &V::Measure) -> bool,
+{
+    // digits and nodes are never wider than the largest digit capacity, so suffix
+    // measures are kept on the stack
+    if slice.len() <= 4 {
+        search_with::<V, F, 4>(slice, left, right, pred)
+    } else {
+        search_with::<V, F, MAX_CAPACITY>(slice, left, right, pred)
+    }
+}
+
+/// Search `slice` of at most `N` elements, with measures of elements following each
+/// element computed in one reverse pass
+fn search_with<'a, V, F, const N: usize>(
+    slice: &'a [V],
+    mut left: V::Measure,
+    mut suffix: V::Measure,
+    pred: &mut F,
+) -> (V::Measure, V::Measure, &'a V)
+where
+    V: Measured,
+    F: FnMut(&V::Measure, &V::Measure) -> bool,
+{
+    // suffixes are pushed from the last element, so they are popped in order
+    let mut suffixes = ArrayVec::<V::Measure, N>::new();
+    for value in slice[1..].iter().rev() {
+        let next_suffix = value.measure().join(&suffix);
+        suffixes.push(suffix);
+        suffix = next_suffix;
+    }
+    for value in &slice[..slice.len() - 1] {
+        let next_left = left.join(&value.measure());
+        if pred(&next_left, &suffix) {
+            return (left, suffix, value);
+        }
+        left = next_left;
+        suffix = suffixes.pop().expect("suffix of each element is computed");
+    }
+    (left, suffix, &slice[slice.len() - 1])
+}
+
+impl<'a, V, W, R> Add<R> for &'a Digit<V, W>
 where
     V: Clone,
+    W: Clone + AsRef<[V]> + FromIterator<V>,
     R: AsRef<[V]>,
 {
-    type Output = Digit<V>;
+    type Output = Digit<V, W>;
 
     fn add(self, other: R) -> Self::Output {
-        match (self.as_ref(), other.as_ref()) {
-            (_, []) => self.clone(),
-            ([v0], [v1]) => Digit::Two([v0.clone(), v1.clone()]),
-            ([v0], [v1, v2]) => Digit::Three([v0.clone(), v1.clone(), v2.clone()]),
-            ([v0], [v1, v2, v3]) => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
-            ([v0, v1], [v2]) => Digit::Three([v0.clone(), v1.clone(), v2.clone()]),
-            ([v0, v1], [v2, v3]) => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
-            ([v0, v1, v2], [v3]) => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
-            _ => panic!(
-                "impossible to create digit of size: {}",
-                self.as_ref().len() + other.as_ref().len(),
-            ),
+        match other.as_ref() {
+            [] => self.clone(),
+            other => Digit::concat(self.as_ref(), other),
         }
     }
 }
 
-impl<V: Measured> Measured for Digit<V> {
+impl<V, W> Measured for Digit<V, W>
+where
+    V: Measured,
+    W: Clone + AsRef<[V]>,
+{
     type Measure = V::Measure;
 
     fn measure(&self) -> Self::Measure {
@@ -89,47 +243,105 @@ impl<V: Measured> Measured for Digit<V> {
     }
 }
 
-impl<V> AsRef<[V]> for Digit<V> {
+impl<V, W> AsRef<[V]> for Digit<V, W>
+where
+    W: AsRef<[V]>,
+{
     fn as_ref(&self) -> &[V] {
         match self {
             Digit::One(v) => v,
             Digit::Two(v) => v,
             Digit::Three(v) => v,
             Digit::Four(v) => v,
+            Digit::Many(v) => v.as_ref(),
         }
     }
 }
 
-impl<'a, V> From<&'a [V]> for Digit<V>
+impl<'a, V, W> From<&'a [V]> for Digit<V, W>
 where
     V: Clone,
+    W: FromIterator<V>,
 {
-    fn from(slice: &'a [V]) -> Digit<V> {
+    fn from(slice: &'a [V]) -> Digit<V, W> {
         match slice {
             [v0] => Digit::One([v0.clone()]),
             [v0, v1] => Digit::Two([v0.clone(), v1.clone()]),
             [v0, v1, v2] => Digit::Three([v0.clone(), v1.clone(), v2.clone()]),
             [v0, v1, v2, v3] => Digit::Four([v0.clone(), v1.clone(), v2.clone(), v3.clone()]),
-            _ => panic!("immposible to create digit from of size: {}", slice.len()),
+            [] => panic!("impossible to create empty digit"),
+            _ => Digit::many(slice, &[]),
         }
     }
 }
 
-impl<'a, R, V: Measured> From<&'a Node<R, V>> for Digit<Node<R, V>>
+impl<'a, R, V: Measured> From<&'a Node<R, V>> for NodeDigit<R, V>
 where
     R: Refs<V>,
     V: Measured,
 {
-    fn from(node: &'a Node<R, V>) -> Digit<Node<R, V>> {
-        match node.as_ref() {
-            NodeInner::Leaf(..) => Digit::One([node.clone()]),
-            NodeInner::Node2 { left, right, .. } => Digit::Two([left.clone(), right.clone()]),
-            NodeInner::Node3 {
-                left,
-                middle,
-                right,
-                ..
-            } => Digit::Three([left.clone(), middle.clone(), right.clone()]),
+    fn from(node: &'a Node<R, V>) -> NodeDigit<R, V> {
+        match node.view() {
+            NodeView::Leaf(..) => Digit::One([node.clone()]),
+            NodeView::Branch(children) => Digit::from(children),
         }
     }
 }
+
+/// Largest supported digit capacity
+const MAX_CAPACITY: usize = 64;
+
+/// Digit capacity as a type, which selects storage of digits wider than four nodes
+///
+/// Only visible to define custom [`Refs`](trait.Refs.html), capacities from 4 to 64
+/// are supported.
+pub enum Capacity<const D: usize> {}
+
+/// Storage of digits wider than four nodes
+pub trait WideDigits {
+    /// Nodes of a wide digit stored inline
+    type Nodes<T: Clone>: Clone + AsRef<[T]> + FromIterator<T>;
+}
+
+/// Storage of digits which never hold more than four nodes, it can not be constructed
+pub struct Narrow<T> {
+    pub(crate) never: Infallible,
+    values: PhantomData<T>,
+}
+
+impl<T> Clone for Narrow<T> {
+    fn clone(&self) -> Self {
+        match self.never {}
+    }
+}
+
+impl<T> AsRef<[T]> for Narrow<T> {
+    fn as_ref(&self) -> &[T] {
+        match self.never {}
+    }
+}
+
+impl<T> FromIterator<T> for Narrow<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(_values: I) -> Self {
+        panic!("digit can not hold more than four nodes")
+    }
+}
+
+impl WideDigits for Capacity<4> {
+    type Nodes<T: Clone> = Narrow<T>;
+}
+
+macro_rules! wide_digits {
+    ($($capacity:literal)*) => {
+        $(
+            impl WideDigits for Capacity<$capacity> {
+                type Nodes<T: Clone> = ArrayVec<T, $capacity>;
+            }
+        )*
+    };
+}
+
+wide_digits!(
+    5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34
+    35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64
+);
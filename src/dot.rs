@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::digit::{Digit, Narrow, NodeDigit};
+use crate::measure::Measured;
+use crate::node::{Node, NodePeek, NodeView};
+use crate::reference::Refs;
+use crate::tree::{Tree, TreeInner};
+use crate::{FingerTree, Inline, Repr};
+
+/// Writer of graphviz DOT description of finger trees, which emits each node
+/// and spine level only once, so nodes shared between versions are visible
+pub(crate) struct Dot<F> {
+    out: String,
+    seen: HashSet<*const ()>,
+    label: F,
+}
+
+impl<F> Dot<F> {
+    pub(crate) fn new(label: F) -> Self {
+        let mut out = String::new();
+        out.push_str("digraph fingertree {\n");
+        out.push_str("  node [fontname=monospace];\n");
+        Dot {
+            out,
+            seen: HashSet::new(),
+            label,
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: &str) {
+        let _ = writeln!(self.out, "  {} -> {} [label=\"{}\"];", from, to, label);
+    }
+
+    /// Emit root of a tree version and all its content
+    pub(crate) fn version<R, V>(&mut self, index: usize, ft: &FingerTree<R, V>)
+    where
+        R: Refs<V>,
+        V: Measured,
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        let root = format!("v{}", index);
+        let _ = writeln!(
+            self.out,
+            "  {} [shape=doubleoctagon, label=\"version {}\"];",
+            root, index
+        );
+        match &ft.repr {
+            Repr::Small { measure, values } => self.small(&root, measure, values),
+            Repr::Tree(tree) => self.tree(&root, "", tree),
+        }
+    }
+
+    /// Emit elements of a small tree, which are stored inline in the tree itself
+    fn small<V>(
+        &mut self,
+        parent: &str,
+        measure: &V::Measure,
+        values: &Digit<Inline<V>, Narrow<Inline<V>>>,
+    ) where
+        V: Measured,
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        let id = format!("{}_small", parent);
+        let label = escape(&(self.label)(measure, None));
+        let _ = writeln!(
+            self.out,
+            "  {} [shape=box, style=rounded, label=\"{}\"];",
+            id, label
+        );
+        self.edge(parent, &id, "");
+        for (index, value) in values.as_ref().iter().enumerate() {
+            let value_id = format!("{}_{}", id, index);
+            let label = escape(&(self.label)(&value.measure, Some(&value.value)));
+            let _ = writeln!(
+                self.out,
+                "  {} [shape=ellipse, label=\"{}\"];",
+                value_id, label
+            );
+            self.edge(&id, &value_id, &index.to_string());
+        }
+    }
+
+    fn tree<R, V>(&mut self, parent: &str, edge: &str, tree: &Tree<R, V>)
+    where
+        R: Refs<V>,
+        V: Measured,
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        match tree {
+            Tree::Empty => {
+                let id = format!("{}_empty", parent);
+                let _ = writeln!(self.out, "  {} [shape=point];", id);
+                self.edge(parent, &id, edge);
+            }
+            Tree::Single(node) => {
+                let id = self.node(node);
+                self.edge(parent, &id, edge);
+            }
+            Tree::Deep(deep) => {
+                let deep: &TreeInner<R, V> = deep;
+                let id = format!("t{:p}", deep);
+                self.edge(parent, &id, edge);
+                if !self
+                    .seen
+                    .insert(deep as *const TreeInner<R, V> as *const ())
+                {
+                    return;
+                }
+                let label = escape(&(self.label)(&deep.measure, None));
+                let _ = writeln!(
+                    self.out,
+                    "  {} [shape=box, style=bold, label=\"{}\"];",
+                    id, label
+                );
+                self.digit(&id, "left", &deep.left);
+                // pending action is not pushed into the spine to show it
+                match deep.peek_spine() {
+                    (Some(..), spine) => self.tree(&id, "lazy spine", &spine),
+                    (None, spine) => self.tree(&id, "spine", &spine),
+                }
+                self.digit(&id, "right", &deep.right);
+            }
+        }
+    }
+
+    fn digit<R, V>(&mut self, parent: &str, side: &str, digit: &NodeDigit<R, V>)
+    where
+        R: Refs<V>,
+        V: Measured,
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        // digits are stored inline in the spine level, so they are emitted with it
+        let id = format!("{}_{}", parent, side);
+        let _ = writeln!(
+            self.out,
+            "  {} [shape=box, style=rounded, label=\"{} {}\"];",
+            id,
+            side,
+            digit.as_ref().len()
+        );
+        self.edge(parent, &id, side);
+        for (index, node) in digit.as_ref().iter().enumerate() {
+            let node = self.node(node);
+            self.edge(&id, &node, &index.to_string());
+        }
+    }
+
+    /// Emit node and its children, returns identifier of the node
+    fn node<R, V>(&mut self, node: &Node<R, V>) -> String
+    where
+        R: Refs<V>,
+        V: Measured,
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        let id = format!("n{:p}", node.as_ptr());
+        if !self.seen.insert(node.as_ptr()) {
+            return id;
+        }
+        let measure = node.measure();
+        match node.peek() {
+            // lazy nodes are shown as they are, without pushing action down
+            NodePeek::Pending(_, inner) => self.lazy(&id, &measure, &inner),
+            NodePeek::Forced(inner) => self.lazy(&id, &measure, inner),
+            NodePeek::View(NodeView::Leaf(value)) => {
+                let label = escape(&(self.label)(&measure, Some(value)));
+                let _ = writeln!(self.out, "  {} [shape=ellipse, label=\"{}\"];", id, label);
+            }
+            NodePeek::View(NodeView::Branch(children)) => {
+                let label = escape(&(self.label)(&measure, None));
+                let shape = match children.len() {
+                    2 => "shape=circle".to_string(),
+                    3 => "shape=triangle".to_string(),
+                    sides => format!("shape=polygon, sides={}", sides),
+                };
+                let _ = writeln!(self.out, "  {} [{}, label=\"{}\"];", id, shape, label);
+                for child in children {
+                    let child = self.node(child);
+                    self.edge(&id, &child, "");
+                }
+            }
+        }
+        id
+    }
+
+    /// Emit lazy node with an edge to the node it wraps
+    fn lazy<R, V>(&mut self, id: &str, measure: &V::Measure, inner: &Node<R, V>)
+    where
+        R: Refs<V>,
+        V: Measured,
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        let label = escape(&(self.label)(measure, None));
+        let _ = writeln!(
+            self.out,
+            "  {} [shape=box, style=dashed, label=\"{}\"];",
+            id, label
+        );
+        let inner = self.node(inner);
+        self.edge(id, &inner, "lazy");
+    }
+}
+
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,133 @@
+//! Errors reported by checked operations of the finger tree
+use std::error::Error;
+use std::fmt;
+
+/// Violation of predicate requirements detected by
+/// [`split_checked`](../struct.FingerTree.html#method.split_checked)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SplitError<M> {
+    /// Predicate returned `true` for the measure of an empty prefix
+    TrueOnUnit,
+    /// Predicate returned `true` for `shorter` accumulated measure but `false` for
+    /// `longer` accumulated measure, where `shorter` is measure of a prefix of elements
+    /// whose measure is `longer`
+    NonMonotonic {
+        /// Measure of the shorter prefix for which predicate returned `true`
+        shorter: M,
+        /// Measure of the longer prefix for which predicate returned `false`
+        longer: M,
+    },
+}
+
+impl<M: fmt::Debug> fmt::Display for SplitError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::TrueOnUnit => {
+                write!(f, "split predicate returned true for empty prefix")
+            }
+            SplitError::NonMonotonic { shorter, longer } => write!(
+                f,
+                "split predicate is not monotonic: true for {:?} but false for {:?}",
+                shorter, longer
+            ),
+        }
+    }
+}
+
+impl<M: fmt::Debug> Error for SplitError<M> {}
+
+/// Violation of structural invariants detected by
+/// [`check_invariants`](../struct.FingerTree.html#method.check_invariants)
+///
+/// `depth` is the level of the spine on which violation was found, where elements
+/// are stored on level zero and each level of the spine adds one level of nesting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantError<M> {
+    /// Leaf node is found above the bottom level
+    LeafDepth {
+        /// Depth of the node
+        depth: usize,
+    },
+    /// Branch node is found on the bottom level
+    BranchDepth,
+    /// Branch node has more children than
+    /// [`NODE_ARITY`](../trait.Refs.html#associatedconstant.NODE_ARITY) of the tree
+    NodeArity {
+        /// Depth of the node
+        depth: usize,
+        /// Number of children of the node
+        arity: usize,
+    },
+    /// Digit holds more nodes than
+    /// [`DIGIT_CAPACITY`](../trait.Refs.html#associatedconstant.DIGIT_CAPACITY) of the tree
+    DigitSize {
+        /// Depth of the spine level
+        depth: usize,
+        /// Number of nodes in the digit
+        size: usize,
+    },
+    /// Measure cached in a node does not match measure of its children, or measure
+    /// of its value for leaves
+    NodeMeasure {
+        /// Depth of the node
+        depth: usize,
+        /// Measure stored in the node
+        cached: M,
+        /// Measure computed from children or value of the node
+        computed: M,
+    },
+    /// Measure cached in a level of the spine does not match measure of its content
+    TreeMeasure {
+        /// Depth of the spine level
+        depth: usize,
+        /// Measure stored in the spine level
+        cached: M,
+        /// Measure computed from digits and spine of the level
+        computed: M,
+    },
+    /// Tree with at most four elements is not stored inline
+    NotInline {
+        /// Number of elements of the tree
+        size: usize,
+    },
+}
+
+impl<M: fmt::Debug> fmt::Display for InvariantError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantError::LeafDepth { depth } => {
+                write!(f, "leaf node with depth: {}", depth)
+            }
+            InvariantError::BranchDepth => write!(f, "all zero depth nodes must be leafs"),
+            InvariantError::NodeArity { depth, arity } => {
+                write!(f, "node with depth {} has {} children", depth, arity)
+            }
+            InvariantError::DigitSize { depth, size } => {
+                write!(f, "digit with depth {} holds {} nodes", depth, size)
+            }
+            InvariantError::NodeMeasure {
+                depth,
+                cached,
+                computed,
+            } => write!(
+                f,
+                "node with depth {} caches measure {:?} instead of {:?}",
+                depth, cached, computed
+            ),
+            InvariantError::TreeMeasure {
+                depth,
+                cached,
+                computed,
+            } => write!(
+                f,
+                "spine with depth {} caches measure {:?} instead of {:?}",
+                depth, cached, computed
+            ),
+            InvariantError::NotInline { size } => {
+                write!(f, "tree with {} elements is not stored inline", size)
+            }
+        }
+    }
+}
+
+impl<M: fmt::Debug> Error for InvariantError<M> {}
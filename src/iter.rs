@@ -1,11 +1,17 @@
 use std::collections::VecDeque;
+use std::convert::Infallible;
 use std::iter::FusedIterator;
+use std::mem;
+use std::ops::{ControlFlow, Range};
+use std::slice;
+use std::vec;
 
-use super::FingerTree;
-use crate::measure::Measured;
-use crate::node::{Node, NodeInner};
+use super::{FingerTree, Inline, Repr};
+use crate::measure::{Counted, Measured};
+use crate::monoid::Monoid;
+use crate::node::{leaf_value, Node, NodeView};
 use crate::reference::Refs;
-use crate::tree::Tree;
+use crate::tree::{RangePiece, Tree};
 
 enum IterFrame<R, V>
 where
@@ -14,6 +20,7 @@ where
 {
     Node(Node<R, V>),
     Tree(Tree<R, V>),
+    Value(V),
 }
 
 impl<'a, R, V> From<&'a Node<R, V>> for IterFrame<R, V>
@@ -36,12 +43,155 @@ where
     }
 }
 
+impl<R, V> IterFrame<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn measure(&self) -> V::Measure {
+        match self {
+            IterFrame::Node(node) => node.measure(),
+            IterFrame::Tree(tree) => tree.measure(),
+            IterFrame::Value(value) => value.measure(),
+        }
+    }
+
+    /// Fold values of the frame until `f` breaks, in which case break value is returned
+    /// together with number of values passed to `f`
+    fn try_fold<A, T, F>(&self, acc: A, reverse: bool, f: &mut F) -> Result<A, (T, usize)>
+    where
+        F: FnMut(A, V) -> T,
+        T: TryOutput<Output = A>,
+    {
+        let mut count = 0;
+        let mut fold = |acc, value: &V| {
+            count += 1;
+            f(acc, value.clone()).branch()
+        };
+        let result = match self {
+            IterFrame::Node(node) => {
+                node.try_fold_leaves(acc, reverse, &mut |acc, _, value| fold(acc, value))
+            }
+            IterFrame::Tree(tree) => {
+                tree.try_fold_leaves(acc, reverse, &mut |acc, _, value| fold(acc, value))
+            }
+            IterFrame::Value(value) => fold(acc, value),
+        };
+        match result {
+            ControlFlow::Continue(acc) => Ok(acc),
+            ControlFlow::Break(output) => Err((output, count)),
+        }
+    }
+
+    /// Value of a frame returned by `expand_back` or `expand_front`
+    fn into_value(self) -> V {
+        match self {
+            IterFrame::Node(node) => leaf_value(&node).clone(),
+            IterFrame::Value(value) => value,
+            IterFrame::Tree(..) => unreachable!("tree frame is never a leaf"),
+        }
+    }
+
+    fn fold<A, F>(&self, acc: A, reverse: bool, f: &mut F) -> A
+    where
+        F: FnMut(A, V) -> A,
+    {
+        let mut fold = |acc, _: &Node<R, V>, value: &V| {
+            ControlFlow::<Infallible, A>::Continue(f(acc, value.clone()))
+        };
+        let result = match self {
+            IterFrame::Node(node) => node.try_fold_leaves(acc, reverse, &mut fold),
+            IterFrame::Tree(tree) => tree.try_fold_leaves(acc, reverse, &mut fold),
+            IterFrame::Value(value) => return f(acc, value.clone()),
+        };
+        match result {
+            ControlFlow::Continue(acc) => acc,
+            ControlFlow::Break(never) => match never {},
+        }
+    }
+}
+
+/// Result of a closure passed to [`Iter::try_fold`](struct.Iter.html#method.try_fold),
+/// stand-in for `std::ops::Try` which is not stable yet
+pub trait TryOutput: Sized {
+    /// Type of the value when closure does not short-circuit
+    type Output;
+
+    /// Wrap value which does not short-circuit
+    fn from_output(output: Self::Output) -> Self;
+
+    /// Either continue with the value, or break with `self`
+    fn branch(self) -> ControlFlow<Self, Self::Output>;
+}
+
+impl<T> TryOutput for Option<T> {
+    type Output = T;
+
+    fn from_output(output: T) -> Self {
+        Some(output)
+    }
+
+    fn branch(self) -> ControlFlow<Self, T> {
+        match self {
+            Some(output) => ControlFlow::Continue(output),
+            None => ControlFlow::Break(None),
+        }
+    }
+}
+
+impl<T, E> TryOutput for Result<T, E> {
+    type Output = T;
+
+    fn from_output(output: T) -> Self {
+        Ok(output)
+    }
+
+    fn branch(self) -> ControlFlow<Self, T> {
+        match self {
+            Ok(output) => ControlFlow::Continue(output),
+            Err(error) => ControlFlow::Break(Err(error)),
+        }
+    }
+}
+
+impl<B, C> TryOutput for ControlFlow<B, C> {
+    type Output = C;
+
+    fn from_output(output: C) -> Self {
+        ControlFlow::Continue(output)
+    }
+
+    fn branch(self) -> ControlFlow<Self, C> {
+        match self {
+            ControlFlow::Continue(output) => ControlFlow::Continue(output),
+            ControlFlow::Break(value) => ControlFlow::Break(ControlFlow::Break(value)),
+        }
+    }
+}
+
+/// Double ended iterator over elements of a finger tree
+///
+/// If values keep track of number of elements in their measure
+/// (see [`Measured::count`](measure/trait.Measured.html#method.count)),
+/// iterator reports exact size and skips whole subtrees in `nth` and `nth_back`,
+/// and it is `ExactSizeIterator` for values marked with
+/// [`Counted`](measure/trait.Counted.html).
+///
+/// `fold`, `rfold` and `for_each` traverse the tree recursively instead of maintaining
+/// a queue of frames, prefer them (or adapters built on top of them such as `sum`
+/// and `collect`) for full scans. `Iterator::try_fold` cannot be overridden on stable
+/// rust, so recursive [`try_fold`](struct.Iter.html#method.try_fold) and
+/// [`try_rfold`](struct.Iter.html#method.try_rfold) are inherent methods, which take
+/// precedence when called on the iterator directly. Adapters built on top of
+/// `Iterator::try_fold`, such as `find`, `any` or `position`, still advance one
+/// element at a time.
 pub struct Iter<R, V>
 where
     R: Refs<V>,
     V: Measured,
 {
     frames: VecDeque<IterFrame<R, V>>,
+    remaining: Option<usize>,
 }
 
 impl<R, V> Iter<R, V>
@@ -51,8 +201,112 @@ where
 {
     pub(crate) fn new(ft: &FingerTree<R, V>) -> Self {
         let mut frames = VecDeque::new();
-        frames.push_back(IterFrame::Tree(ft.rec.clone()));
-        Iter { frames }
+        match &ft.repr {
+            // left most frame must be at the back of the queue
+            Repr::Small { values, .. } => {
+                let values = values.as_ref().iter().rev();
+                frames.extend(values.map(|value| IterFrame::Value(value.value.clone())))
+            }
+            Repr::Tree(tree) => frames.push_back(IterFrame::Tree(tree.clone())),
+        }
+        Iter {
+            frames,
+            remaining: V::count(&ft.measure()),
+        }
+    }
+
+    pub(crate) fn range<S, E>(ft: &FingerTree<R, V>, start: &mut S, end: &mut E) -> Self
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+    {
+        // left most frame must be at the back of the queue
+        let mut iter = Iter {
+            frames: VecDeque::new(),
+            remaining: None,
+        };
+        let mut measure = V::Measure::unit();
+        match &ft.repr {
+            Repr::Small { values, .. } => {
+                let mut prefix = V::Measure::unit();
+                for value in values.as_ref() {
+                    prefix = prefix.join(&value.measure);
+                    if start(&prefix) && !end(&prefix) {
+                        measure = measure.join(&value.measure);
+                        iter.push_front(IterFrame::Value(value.value.clone()));
+                    }
+                }
+            }
+            Repr::Tree(tree) => tree.range_pieces(&V::Measure::unit(), start, end, &mut |piece| {
+                measure = measure.join(&piece.measure());
+                match piece {
+                    RangePiece::Node(node) => iter.push_front(node),
+                    RangePiece::Tree(tree) => iter.push_front(tree),
+                }
+            }),
+        }
+        iter.remaining = V::count(&measure);
+        iter
+    }
+
+    /// Skip elements from the left side until `pred` flips from `false` to `true`
+    ///
+    /// Predicate is called with accumulated measure of remaining elements from the left
+    /// side, and follows the same rules as predicate passed to
+    /// [`split`](struct.FingerTree.html#method.split). After this call iterator starts
+    /// with the element for which predicate flipped, or becomes empty if it never flips.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn seek<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let mut measure = V::Measure::unit();
+        while let Some(frame) = self.frames.pop_back() {
+            let next_measure = measure.join(&frame.measure());
+            if !pred(&next_measure) {
+                // skip whole frame
+                measure = next_measure;
+                continue;
+            }
+            if let Some(leaf) = self.expand_back(frame) {
+                self.push_back(leaf);
+                break;
+            }
+        }
+        self.skipped(&measure);
+    }
+
+    /// Skip elements from the right side until `pred` flips from `false` to `true`
+    ///
+    /// Works the same way as [`seek`](struct.Iter.html#method.seek), but predicate is
+    /// called with accumulated measure of remaining elements from the right side.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn seek_back<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let mut measure = V::Measure::unit();
+        while let Some(frame) = self.frames.pop_front() {
+            let next_measure = frame.measure().join(&measure);
+            if !pred(&next_measure) {
+                measure = next_measure;
+                continue;
+            }
+            if let Some(leaf) = self.expand_front(frame) {
+                self.push_front(leaf);
+                break;
+            }
+        }
+        self.skipped(&measure);
+    }
+
+    /// Update number of remaining elements after elements with `measure` were skipped
+    fn skipped(&mut self, measure: &V::Measure) {
+        if let (Some(remaining), Some(count)) = (self.remaining, V::count(measure)) {
+            self.remaining = Some(remaining - count);
+        }
     }
 
     fn push_back<F: Into<IterFrame<R, V>>>(&mut self, frame: F) {
@@ -62,6 +316,172 @@ where
     fn push_front<F: Into<IterFrame<R, V>>>(&mut self, frame: F) {
         self.frames.push_front(frame.into())
     }
+
+    /// Push children of the frame to the back of the queue, or return the frame itself
+    /// if it is a leaf
+    fn expand_back(&mut self, frame: IterFrame<R, V>) -> Option<IterFrame<R, V>> {
+        match frame {
+            IterFrame::Value(..) => return Some(frame),
+            IterFrame::Node(node) => match node.view() {
+                NodeView::Leaf(..) => return Some(IterFrame::Node(node)),
+                NodeView::Branch([left, right]) => {
+                    self.push_back(right);
+                    self.push_back(left);
+                }
+                NodeView::Branch([left, middle, right]) => {
+                    self.push_back(right);
+                    self.push_back(middle);
+                    self.push_back(left);
+                }
+                NodeView::Branch(children) => {
+                    for child in children.iter().rev() {
+                        self.push_back(child);
+                    }
+                }
+            },
+            IterFrame::Tree(tree) => match tree {
+                Tree::Empty => (),
+                Tree::Single(node) => self.push_back(&node),
+                Tree::Deep(deep) => {
+                    for node in deep.right.as_ref().iter().rev() {
+                        self.push_back(node);
+                    }
+                    self.push_back(deep.spine());
+                    for node in deep.left.as_ref().iter().rev() {
+                        self.push_back(node);
+                    }
+                }
+            },
+        }
+        None
+    }
+
+    /// Push children of the frame to the front of the queue, or return the frame itself
+    /// if it is a leaf
+    fn expand_front(&mut self, frame: IterFrame<R, V>) -> Option<IterFrame<R, V>> {
+        match frame {
+            IterFrame::Value(..) => return Some(frame),
+            IterFrame::Node(node) => match node.view() {
+                NodeView::Leaf(..) => return Some(IterFrame::Node(node)),
+                NodeView::Branch([left, right]) => {
+                    self.push_front(left);
+                    self.push_front(right);
+                }
+                NodeView::Branch([left, middle, right]) => {
+                    self.push_front(left);
+                    self.push_front(middle);
+                    self.push_front(right);
+                }
+                NodeView::Branch(children) => {
+                    for child in children {
+                        self.push_front(child);
+                    }
+                }
+            },
+            IterFrame::Tree(tree) => match tree {
+                Tree::Empty => (),
+                Tree::Single(node) => self.push_front(&node),
+                Tree::Deep(deep) => {
+                    for node in deep.left.as_ref() {
+                        self.push_front(node);
+                    }
+                    self.push_front(deep.spine());
+                    for node in deep.right.as_ref() {
+                        self.push_front(node);
+                    }
+                }
+            },
+        }
+        None
+    }
+
+    /// Recursive version of `Iterator::try_fold`, accepting closures which return
+    /// `Option`, `Result` or `ControlFlow`
+    ///
+    /// When `f` short-circuits, iterator stays positioned right after the element
+    /// it short-circuited on.
+    pub fn try_fold<A, F, T>(&mut self, init: A, mut f: F) -> T
+    where
+        F: FnMut(A, V) -> T,
+        T: TryOutput<Output = A>,
+    {
+        let mut acc = init;
+        while let Some(frame) = self.frames.pop_back() {
+            match frame.try_fold(acc, false, &mut f) {
+                Ok(next) => {
+                    self.skipped(&frame.measure());
+                    acc = next;
+                }
+                Err((output, consumed)) => {
+                    // drop values of the frame which were already passed to `f`
+                    self.frames.push_back(frame);
+                    for _ in 0..consumed {
+                        self.next_leaf();
+                    }
+                    return output;
+                }
+            }
+        }
+        T::from_output(acc)
+    }
+
+    /// Recursive version of `DoubleEndedIterator::try_rfold`, see
+    /// [`try_fold`](struct.Iter.html#method.try_fold)
+    pub fn try_rfold<A, F, T>(&mut self, init: A, mut f: F) -> T
+    where
+        F: FnMut(A, V) -> T,
+        T: TryOutput<Output = A>,
+    {
+        let mut acc = init;
+        while let Some(frame) = self.frames.pop_front() {
+            match frame.try_fold(acc, true, &mut f) {
+                Ok(next) => {
+                    self.skipped(&frame.measure());
+                    acc = next;
+                }
+                Err((output, consumed)) => {
+                    self.frames.push_front(frame);
+                    for _ in 0..consumed {
+                        self.next_back_leaf();
+                    }
+                    return output;
+                }
+            }
+        }
+        T::from_output(acc)
+    }
+
+    /// Next leaf frame from the left side
+    fn next_leaf(&mut self) -> Option<IterFrame<R, V>> {
+        loop {
+            let frame = self.frames.pop_back()?;
+            if let Some(leaf) = self.expand_back(frame) {
+                self.remaining = self.remaining.map(|remaining| remaining - 1);
+                return Some(leaf);
+            }
+        }
+    }
+
+    /// Next leaf frame from the right side
+    fn next_back_leaf(&mut self) -> Option<IterFrame<R, V>> {
+        loop {
+            let frame = self.frames.pop_front()?;
+            if let Some(leaf) = self.expand_front(frame) {
+                self.remaining = self.remaining.map(|remaining| remaining - 1);
+                return Some(leaf);
+            }
+        }
+    }
+
+    /// Next value from the left side
+    fn next_value(&mut self) -> Option<V> {
+        self.next_leaf().map(IterFrame::into_value)
+    }
+
+    /// Next value from the right side
+    fn next_back_value(&mut self) -> Option<V> {
+        self.next_back_leaf().map(IterFrame::into_value)
+    }
 }
 
 impl<R, V> FusedIterator for Iter<R, V>
@@ -79,46 +499,51 @@ where
     type Item = V;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.frames.pop_back()? {
-                IterFrame::Node(node) => match node.as_ref() {
-                    NodeInner::Leaf(value) => return Some(value.clone()),
-                    NodeInner::Node2 { left, right, .. } => {
-                        self.push_back(right);
-                        self.push_back(left);
-                        continue;
-                    }
-                    NodeInner::Node3 {
-                        left,
-                        middle,
-                        right,
-                        ..
-                    } => {
-                        self.push_back(right);
-                        self.push_back(middle);
-                        self.push_back(left);
-                        continue;
-                    }
-                },
-                IterFrame::Tree(tree) => match tree {
-                    Tree::Empty => continue,
-                    Tree::Single(node) => {
-                        self.push_back(&node);
-                        continue;
-                    }
-                    Tree::Deep(deep) => {
-                        for node in deep.right.as_ref().iter().rev() {
-                            self.push_back(node);
-                        }
-                        self.push_back(&deep.spine);
-                        for node in deep.left.as_ref().iter().rev() {
-                            self.push_back(node);
-                        }
-                        continue;
-                    }
-                },
+        self.next_value()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining {
+            Some(remaining) => (remaining, Some(remaining)),
+            None if self.frames.is_empty() => (0, Some(0)),
+            None => (0, None),
+        }
+    }
+
+    fn count(self) -> usize {
+        match self.remaining {
+            Some(remaining) => remaining,
+            None => self.fold(0, |count, _| count + 1),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.remaining.is_none() {
+            for _ in 0..n {
+                self.next_value()?;
             }
+        } else {
+            self.seek(|measure| V::count(measure).unwrap_or(0) > n);
+        }
+        self.next()
+    }
+
+    fn fold<A, F>(mut self, init: A, mut f: F) -> A
+    where
+        F: FnMut(A, Self::Item) -> A,
+    {
+        let mut acc = init;
+        while let Some(frame) = self.frames.pop_back() {
+            acc = frame.fold(acc, false, &mut f);
         }
+        acc
+    }
+
+    fn for_each<F>(self, mut f: F)
+    where
+        F: FnMut(Self::Item),
+    {
+        self.fold((), |(), value| f(value))
     }
 }
 
@@ -128,42 +553,431 @@ where
     V: Measured,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
+        self.next_back_value()
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        if self.remaining.is_none() {
+            for _ in 0..n {
+                self.next_back_value()?;
+            }
+        } else {
+            self.seek_back(|measure| V::count(measure).unwrap_or(0) > n);
+        }
+        self.next_back()
+    }
+
+    fn rfold<A, F>(mut self, init: A, mut f: F) -> A
+    where
+        F: FnMut(A, Self::Item) -> A,
+    {
+        let mut acc = init;
+        while let Some(frame) = self.frames.pop_front() {
+            acc = frame.fold(acc, true, &mut f);
+        }
+        acc
+    }
+}
+
+impl<R, V> ExactSizeIterator for Iter<R, V>
+where
+    R: Refs<V>,
+    V: Counted,
+{
+}
+
+/// Double ended iterator which yields elements together with accumulated measure
+/// of all elements before them, and when iterated backward with accumulated measure
+/// of all elements after them
+pub struct PrefixIter<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    iter: Iter<R, V>,
+    prefix: V::Measure,
+    suffix: V::Measure,
+}
+
+impl<R, V> PrefixIter<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    pub(crate) fn new(ft: &FingerTree<R, V>) -> Self {
+        PrefixIter {
+            iter: Iter::new(ft),
+            prefix: V::Measure::unit(),
+            suffix: V::Measure::unit(),
+        }
+    }
+}
+
+impl<R, V> FusedIterator for PrefixIter<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+}
+
+impl<R, V> Iterator for PrefixIter<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    type Item = (V::Measure, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // leaves cache their measure, so values are not measured again
+        let leaf = self.iter.next_leaf()?;
+        let prefix = self.prefix.join(&leaf.measure());
+        let prefix = mem::replace(&mut self.prefix, prefix);
+        Some((prefix, leaf.into_value()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<R, V> DoubleEndedIterator for PrefixIter<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let leaf = self.iter.next_back_leaf()?;
+        let suffix = leaf.measure().join(&self.suffix);
+        let suffix = mem::replace(&mut self.suffix, suffix);
+        Some((suffix, leaf.into_value()))
+    }
+}
+
+/// Iterator over consecutive subtrees with accumulated measure bounded by a predicate,
+/// see [`chunks_by_measure`](struct.FingerTree.html#method.chunks_by_measure)
+pub struct Chunks<R, V, F>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    tail: FingerTree<R, V>,
+    limit: F,
+}
+
+impl<R, V, F> Chunks<R, V, F>
+where
+    R: Refs<V>,
+    V: Measured,
+    F: FnMut(&V::Measure) -> bool,
+{
+    pub(crate) fn new(ft: &FingerTree<R, V>, limit: F) -> Self {
+        Chunks {
+            tail: ft.clone(),
+            limit,
+        }
+    }
+}
+
+impl<R, V, F> FusedIterator for Chunks<R, V, F>
+where
+    R: Refs<V>,
+    V: Measured,
+    F: FnMut(&V::Measure) -> bool,
+{
+}
+
+impl<R, V, F> Iterator for Chunks<R, V, F>
+where
+    R: Refs<V>,
+    V: Measured,
+    F: FnMut(&V::Measure) -> bool,
+{
+    type Item = FingerTree<R, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.tail.is_empty() {
+            return None;
+        }
+        let (chunk, tail) = self.tail.split(&mut self.limit);
+        if chunk.is_empty() {
+            // first element exceeds limit on its own
+            let (head, tail) = self.tail.view_left()?;
+            self.tail = tail;
+            Some(FingerTree::new().push_right(head))
+        } else {
+            self.tail = tail;
+            Some(chunk)
+        }
+    }
+}
+
+/// Run of consecutive elements which are stored together in a single digit or node
+///
+/// Each element of the tree is stored in its own leaf, so elements of a run are not
+/// contiguous in memory and cannot be borrowed as `&[V]`. Run borrows the digit or
+/// node holding the leaves instead, so it neither copies nor allocates anything.
+pub struct LeafRun<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    leaves: Leaves<'a, R, V>,
+}
+
+/// Storage of leaves of a run
+enum Leaves<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    /// Leaf nodes of a digit or of a node of the bottom level
+    Nodes(&'a [Node<R, V>]),
+    /// Values of a tree stored inline
+    Inline(&'a [Inline<V>]),
+}
+
+impl<'a, R, V> LeafRun<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn nodes(nodes: &'a [Node<R, V>]) -> Self {
+        LeafRun {
+            leaves: Leaves::Nodes(nodes),
+        }
+    }
+
+    fn inline(values: &'a [Inline<V>]) -> Self {
+        LeafRun {
+            leaves: Leaves::Inline(values),
+        }
+    }
+
+    /// Number of elements in the run
+    pub fn len(&self) -> usize {
+        match self.leaves {
+            Leaves::Nodes(nodes) => nodes.len(),
+            Leaves::Inline(values) => values.len(),
+        }
+    }
+
+    /// Returns `true` if the run is empty, which never happens for runs returned by
+    /// [`leaf_runs`](struct.FingerTree.html#method.leaf_runs)
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Element at `index` position in the run
+    pub fn get(&self, index: usize) -> Option<&'a V> {
+        match self.leaves {
+            Leaves::Nodes(nodes) => nodes.get(index).map(leaf_value),
+            Leaves::Inline(values) => values.get(index).map(|value| &value.value),
+        }
+    }
+
+    /// Iterator over references to elements of the run
+    pub fn iter(&self) -> LeafRunIter<'a, R, V> {
+        LeafRunIter {
+            run: self.clone(),
+            range: 0..self.len(),
+        }
+    }
+}
+
+impl<'a, R, V> IntoIterator for LeafRun<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    type Item = &'a V;
+    type IntoIter = LeafRunIter<'a, R, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<R, V> Measured for LeafRun<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    type Measure = V::Measure;
+
+    fn measure(&self) -> Self::Measure {
+        match self.leaves {
+            Leaves::Nodes(nodes) => nodes.measure(),
+            Leaves::Inline(values) => values.measure(),
+        }
+    }
+}
+
+impl<R, V> Clone for LeafRun<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn clone(&self) -> Self {
+        LeafRun {
+            leaves: self.leaves,
+        }
+    }
+}
+
+impl<R, V> Clone for Leaves<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R, V> Copy for Leaves<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+}
+
+/// Double ended iterator over references to elements of a
+/// [`LeafRun`](struct.LeafRun.html)
+pub struct LeafRunIter<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    run: LeafRun<'a, R, V>,
+    range: Range<usize>,
+}
+
+impl<'a, R, V> Iterator for LeafRunIter<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.run.get(self.range.next()?)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<R, V> DoubleEndedIterator for LeafRunIter<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.run.get(self.range.next_back()?)
+    }
+}
+
+impl<R, V> ExactSizeIterator for LeafRunIter<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+}
+
+impl<R, V> FusedIterator for LeafRunIter<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+}
+
+enum RunFrame<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    Node(&'a Node<R, V>),
+    Tree(&'a Tree<R, V>),
+    Run(LeafRun<'a, R, V>),
+}
+
+/// Iterator over runs of consecutive elements stored together in the tree,
+/// see [`leaf_runs`](struct.FingerTree.html#method.leaf_runs)
+pub struct LeafRuns<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    frames: Vec<RunFrame<'a, R, V>>,
+}
+
+impl<'a, R, V> LeafRuns<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    pub(crate) fn new(ft: &'a FingerTree<R, V>) -> Self {
+        let frame = match &ft.repr {
+            Repr::Small { values, .. } => RunFrame::Run(LeafRun::inline(values.as_ref())),
+            Repr::Tree(tree) => RunFrame::Tree(tree),
+        };
+        LeafRuns {
+            frames: vec![frame],
+        }
+    }
+}
+
+fn is_leaf<R, V>(node: &Node<R, V>) -> bool
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    matches!(node.view(), NodeView::Leaf(..))
+}
+
+impl<R, V> FusedIterator for LeafRuns<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+}
+
+impl<'a, R, V> Iterator for LeafRuns<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    type Item = LeafRun<'a, R, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
         loop {
-            match self.frames.pop_front()? {
-                IterFrame::Node(node) => match node.as_ref() {
-                    NodeInner::Leaf(value) => return Some(value.clone()),
-                    NodeInner::Node2 { left, right, .. } => {
-                        self.push_front(left);
-                        self.push_front(right);
-                        continue;
-                    }
-                    NodeInner::Node3 {
-                        left,
-                        middle,
-                        right,
-                        ..
-                    } => {
-                        self.push_front(left);
-                        self.push_front(middle);
-                        self.push_front(right);
-                        continue;
-                    }
-                },
-                IterFrame::Tree(tree) => match tree {
-                    Tree::Empty => continue,
-                    Tree::Single(node) => {
-                        self.push_front(&node);
-                        continue;
-                    }
+            match self.frames.pop()? {
+                RunFrame::Run(run) => return Some(run),
+                RunFrame::Tree(tree) => match tree {
+                    Tree::Empty => (),
+                    Tree::Single(node) => self.frames.push(RunFrame::Node(node)),
                     Tree::Deep(deep) => {
-                        for node in deep.left.as_ref() {
-                            self.push_front(node);
+                        let (left, right) = (deep.left.as_ref(), deep.right.as_ref());
+                        if is_leaf(&left[0]) {
+                            self.frames.push(RunFrame::Run(LeafRun::nodes(right)));
+                            self.frames.push(RunFrame::Tree(deep.spine()));
+                            self.frames.push(RunFrame::Run(LeafRun::nodes(left)));
+                        } else {
+                            self.frames.extend(right.iter().rev().map(RunFrame::Node));
+                            self.frames.push(RunFrame::Tree(deep.spine()));
+                            self.frames.extend(left.iter().rev().map(RunFrame::Node));
                         }
-                        self.push_front(&deep.spine);
-                        for node in deep.right.as_ref() {
-                            self.push_front(node);
+                    }
+                },
+                RunFrame::Node(node) => match node.view() {
+                    NodeView::Leaf(..) => return Some(LeafRun::nodes(slice::from_ref(node))),
+                    NodeView::Branch(children) => {
+                        if is_leaf(&children[0]) {
+                            return Some(LeafRun::nodes(children));
                         }
-                        continue;
+                        self.frames
+                            .extend(children.iter().rev().map(RunFrame::Node));
                     }
                 },
             }
@@ -18,6 +18,8 @@
 //!    described in the paper. As rust's monomorphization does not play well with such types.
 //!  - Implementation abstracts over reference counted types `Rc/Arc`. Using type family trick.
 //!  - Uses strict spine in implementation.
+//!  - Trees with at most four elements store them inline, so tiny sequences do not
+//!    allocate at all.
 //!  - Iterator returns cloned value, and in general this implementation assumes that value
 //!    stored in a tree is cheaply clonable, if it is not you can always put it in a `Rc/Arc` or
 //!    anything else.
@@ -58,18 +60,29 @@
 #![deny(missing_docs)]
 #![deny(warnings)]
 
+pub mod action;
+mod chunked;
 mod digit;
+mod dot;
+pub mod error;
 mod iter;
 pub mod measure;
+mod measurer;
 pub mod monoid;
 mod node;
+mod ordered;
 mod reference;
+pub mod stats;
 mod tree;
 
 #[cfg(test)]
 mod test;
 
-pub use crate::measure::Measured;
+pub use crate::action::Action;
+pub use crate::chunked::{Chunk, ChunkRefs, ChunkedFingerTree};
+pub use crate::digit::{Capacity, Narrow, WideDigits};
+pub use crate::measure::{Measured, Measurer};
+pub use crate::measurer::MeasurerTree;
 pub use crate::monoid::Monoid;
 pub use crate::node::NodeInner;
 pub use crate::reference::{ArcRefs, RcRefs, Ref, Refs};
@@ -91,13 +104,36 @@ pub mod sync {
     pub type FingerTree<V> = super::FingerTree<super::ArcRefs, V>;
 }
 
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::fmt;
 use std::iter::FromIterator;
-use std::ops::Add;
+use std::mem;
+use std::ops::{Add, Bound, ControlFlow, RangeBounds};
 
-use crate::iter::Iter;
-use crate::node::Node;
-use crate::tree::Tree;
+use crate::action::FromAction;
+use crate::digit::Digit;
+use crate::dot::Dot;
+use crate::error::{InvariantError, SplitError};
+use crate::iter::{Chunks, Iter, LeafRuns, PrefixIter};
+use crate::measure::{Counted, DefaultMeasurer, WithMeasure};
+use crate::node::{leaf_value, Node, NodeBuf};
+use crate::stats::Stats;
+use crate::tree::{SplitCheck, Tree};
+
+/// Maximum number of nodes passed directly to the middle of concatenation, as they
+/// are pushed one by one and are kept in a fixed size buffer, larger number of nodes
+/// is converted to a balanced tree first
+const CONCAT_MIDDLE_MAX: usize = 16;
+
+/// Unwrap result of fallible operation called with infallible predicate
+pub(crate) fn infallible<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
 
 /// FingerTree implementation
 ///
@@ -112,7 +148,37 @@ where
     R: Refs<V>,
     V: Measured,
 {
-    pub(crate) rec: Tree<R, V>,
+    repr: Repr<R, V>,
+}
+
+/// Representation of the finger tree
+///
+/// Trees with up to four elements keep them inline without allocating any nodes,
+/// all constructors go through [`from_rec`](struct.FingerTree.html#method.from_rec),
+/// so such trees are always stored as `Small`. Inline values keep their measure
+/// the same way leaves do, so each value is measured only once.
+enum Repr<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    Small {
+        measure: V::Measure,
+        values: Digit<Inline<V>, Narrow<Inline<V>>>,
+    },
+    Tree(Tree<R, V>),
+}
+
+/// Value of an inline tree stored together with its measure
+pub(crate) type Inline<V> = WithMeasure<V, DefaultMeasurer>;
+
+/// Leaf node holding inline value, which reuses its measure
+fn inline_leaf<R, V>(inline: &Inline<V>) -> Node<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    Node::leaf_with_measure(inline.value.clone(), inline.measure.clone())
 }
 
 impl<R, V> Clone for FingerTree<R, V>
@@ -121,9 +187,14 @@ where
     V: Measured,
 {
     fn clone(&self) -> Self {
-        FingerTree {
-            rec: self.rec.clone(),
-        }
+        let repr = match &self.repr {
+            Repr::Small { measure, values } => Repr::Small {
+                measure: measure.clone(),
+                values: values.clone(),
+            },
+            Repr::Tree(tree) => Repr::Tree(tree.clone()),
+        };
+        FingerTree { repr }
     }
 }
 
@@ -132,26 +203,149 @@ where
     R: Refs<V>,
     V: Measured,
 {
+    /// Construct finger tree from its recursive representation, trees with
+    /// at most four elements are converted to the inline representation
+    pub(crate) fn from_rec(rec: Tree<R, V>) -> Self {
+        let maybe_small = match &rec {
+            Tree::Empty => false,
+            Tree::Single(..) => true,
+            // spine node holds at least two elements
+            Tree::Deep(deep) => match deep.spine() {
+                Tree::Empty => true,
+                Tree::Single(..) => deep.left.as_ref().len() + deep.right.as_ref().len() <= 2,
+                Tree::Deep(..) => false,
+            },
+        };
+        if !maybe_small {
+            return FingerTree {
+                repr: Repr::Tree(rec),
+            };
+        }
+        // larger trees are recognized after visiting at most five leaves
+        let mut leaves: [Option<Inline<V>>; 4] = Default::default();
+        let small = rec.try_fold_leaves(0, false, &mut |len, node, value| {
+            if len == leaves.len() {
+                return ControlFlow::Break(());
+            }
+            leaves[len] = Some(Inline::from_parts(value.clone(), node.measure()));
+            ControlFlow::Continue(len + 1)
+        });
+        let small = match (small, leaves) {
+            (ControlFlow::Continue(_), [Some(v0), None, None, None]) => Digit::One([v0]),
+            (ControlFlow::Continue(_), [Some(v0), Some(v1), None, None]) => Digit::Two([v0, v1]),
+            (ControlFlow::Continue(_), [Some(v0), Some(v1), Some(v2), None]) => {
+                Digit::Three([v0, v1, v2])
+            }
+            (ControlFlow::Continue(_), [Some(v0), Some(v1), Some(v2), Some(v3)]) => {
+                Digit::Four([v0, v1, v2, v3])
+            }
+            _ => {
+                return FingerTree {
+                    repr: Repr::Tree(rec),
+                }
+            }
+        };
+        FingerTree {
+            repr: Repr::Small {
+                measure: rec.measure(),
+                values: small,
+            },
+        }
+    }
+
+    /// Construct inline finger tree from at most four values
+    fn from_small(values: &[Inline<V>]) -> Self {
+        if values.is_empty() {
+            return Self::new();
+        }
+        let values = Digit::from(values);
+        FingerTree {
+            repr: Repr::Small {
+                measure: values.measure(),
+                values,
+            },
+        }
+    }
+
+    /// Recursive representation of the tree, inline trees are represented by `empty`
+    /// tree with their values appended to `leaves` instead
+    fn rec_or_leaves<'a>(
+        &'a self,
+        empty: &'a Tree<R, V>,
+        leaves: &mut NodeBuf<R, V>,
+    ) -> &'a Tree<R, V> {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                for value in values.as_ref() {
+                    leaves.push(inline_leaf(value));
+                }
+                empty
+            }
+            Repr::Tree(tree) => tree,
+        }
+    }
+
+    /// Recursive representation of the tree, which is only constructed for
+    /// trees stored inline
+    pub(crate) fn rec(&self) -> Cow<'_, Tree<R, V>> {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let mut nodes: Digit<_, Narrow<_>> = values.map(inline_leaf);
+                Cow::Owned(match &mut nodes {
+                    Digit::One(nodes) => tree::build(nodes),
+                    Digit::Two(nodes) => tree::build(nodes),
+                    Digit::Three(nodes) => tree::build(nodes),
+                    Digit::Four(nodes) => tree::build(nodes),
+                    Digit::Many(nodes) => match nodes.never {},
+                })
+            }
+            Repr::Tree(tree) => Cow::Borrowed(tree),
+        }
+    }
+
     /// Constructs a new, empty `FingerTree`
     ///
     /// Complexity: `O(1)`
     pub fn new() -> Self {
-        FingerTree { rec: Tree::empty() }
+        FingerTree {
+            repr: Repr::Tree(Tree::empty()),
+        }
     }
 
     /// Returns `true` if finger tree is empty
     ///
     /// Complexity: `O(1)`
     pub fn is_empty(&self) -> bool {
-        matches!(self.rec, Tree::Empty)
+        matches!(self.repr, Repr::Tree(Tree::Empty))
     }
 
     /// Creates new tree with value prepended to the left side of the tree
     ///
     /// Amortized complexity: `O(1)`
     pub fn push_left(&self, value: V) -> Self {
-        FingerTree {
-            rec: self.rec.push_left(Node::leaf(value)),
+        match &self.repr {
+            Repr::Small { measure, values } => match values {
+                Digit::Four(values) => FingerTree {
+                    repr: Repr::Tree(Tree::deep(
+                        Digit::One([Node::leaf(value)]),
+                        Tree::empty(),
+                        Digit::Four(values.each_ref().map(inline_leaf)),
+                    )),
+                },
+                _ => {
+                    let value = Inline::from(value);
+                    FingerTree {
+                        repr: Repr::Small {
+                            measure: value.measure.join(measure),
+                            values: &Digit::One([value]) + values,
+                        },
+                    }
+                }
+            },
+            Repr::Tree(Tree::Empty) => Self::from_small(&[Inline::from(value)]),
+            Repr::Tree(tree) => FingerTree {
+                repr: Repr::Tree(tree.push_left(Node::leaf(value))),
+            },
         }
     }
 
@@ -159,8 +353,29 @@ where
     ///
     /// Amortized complexity: `O(1)`
     pub fn push_right(&self, value: V) -> Self {
-        FingerTree {
-            rec: self.rec.push_right(Node::leaf(value)),
+        match &self.repr {
+            Repr::Small { measure, values } => match values {
+                Digit::Four(values) => FingerTree {
+                    repr: Repr::Tree(Tree::deep(
+                        Digit::Four(values.each_ref().map(inline_leaf)),
+                        Tree::empty(),
+                        Digit::One([Node::leaf(value)]),
+                    )),
+                },
+                _ => {
+                    let value = Inline::from(value);
+                    FingerTree {
+                        repr: Repr::Small {
+                            measure: measure.join(&value.measure),
+                            values: values + [value],
+                        },
+                    }
+                }
+            },
+            Repr::Tree(Tree::Empty) => Self::from_small(&[Inline::from(value)]),
+            Repr::Tree(tree) => FingerTree {
+                repr: Repr::Tree(tree.push_right(Node::leaf(value))),
+            },
         }
     }
 
@@ -170,10 +385,15 @@ where
     ///
     /// Amortized complexity: `O(1)`
     pub fn view_left(&self) -> Option<(V, Self)> {
-        let (head, tail) = self.rec.view_left()?;
-        match head.as_ref() {
-            NodeInner::Leaf(value) => Some((value.clone(), FingerTree { rec: tail })),
-            _ => unreachable!("not leaf returned from top level of the finger-tree"),
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let (head, tail) = values.as_ref().split_first()?;
+                Some((head.value.clone(), Self::from_small(tail)))
+            }
+            Repr::Tree(tree) => {
+                let (head, tail) = tree.view_left()?;
+                Some((leaf_value(&head).clone(), FingerTree::from_rec(tail)))
+            }
         }
     }
 
@@ -183,10 +403,15 @@ where
     ///
     /// Amortized complexity: `O(1)`
     pub fn view_right(&self) -> Option<(V, Self)> {
-        let (head, tail) = self.rec.view_right()?;
-        match head.as_ref() {
-            NodeInner::Leaf(value) => Some((value.clone(), FingerTree { rec: tail })),
-            _ => unreachable!("not leaf returned from top level of the finger-tree"),
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let (head, tail) = values.as_ref().split_last()?;
+                Some((head.value.clone(), Self::from_small(tail)))
+            }
+            Repr::Tree(tree) => {
+                let (head, tail) = tree.view_right()?;
+                Some((leaf_value(&head).clone(), FingerTree::from_rec(tail)))
+            }
         }
     }
 
@@ -203,34 +428,93 @@ where
     pub fn split<F>(&self, mut pred: F) -> (FingerTree<R, V>, FingerTree<R, V>)
     where
         F: FnMut(&V::Measure) -> bool,
+    {
+        infallible(self.try_split(|measure| Ok(pred(measure))))
+    }
+
+    /// Destructure tree into two trees, using provided fallible predicate
+    ///
+    /// Works the same way as [`split`](struct.FingerTree.html#method.split), but stops
+    /// as soon as predicate returns an error, and returns this error.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn try_split<E, F>(&self, mut pred: F) -> Result<(Self, Self), E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
     {
         if self.is_empty() {
-            (Self::new(), Self::new())
-        } else if pred(&self.measure()) {
-            let (l, x, r) = self.rec.split(V::Measure::unit(), &mut pred);
-            (
-                FingerTree { rec: l },
-                FingerTree {
-                    rec: r.push_left(x),
-                },
-            )
+            Ok((Self::new(), Self::new()))
+        } else if pred(&self.measure())? {
+            if let Repr::Small { values, .. } = &self.repr {
+                let (l, _x, _r) = values.split(V::Measure::unit(), &mut pred)?;
+                let (l, r) = values.as_ref().split_at(l.len());
+                return Ok((Self::from_small(l), Self::from_small(r)));
+            }
+            let (l, x, r) = self.rec().split(V::Measure::unit(), &mut pred)?;
+            Ok((
+                FingerTree::from_rec(l),
+                FingerTree::from_rec(r.push_left(x)),
+            ))
         } else {
-            (self.clone(), Self::new())
+            Ok((self.clone(), Self::new()))
         }
     }
 
+    /// Destructure tree into two trees, verifying that predicate is monotonic
+    ///
+    /// Works the same way as [`split`](struct.FingerTree.html#method.split), but checks
+    /// that `pred` returns `false` for the empty prefix and that it does not flip back
+    /// from `true` to `false`. Monotonicity is checked on accumulated measures at the
+    /// boundaries of digits on every level of the tree and of all nodes visited while
+    /// descending to the element on which predicate flips, which costs `O(ln(N))`
+    /// additional calls of the predicate, and violation is reported with the offending
+    /// measures.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn split_checked<F>(&self, mut pred: F) -> Result<(Self, Self), SplitError<V::Measure>>
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        if pred(&V::Measure::unit()) {
+            return Err(SplitError::TrueOnUnit);
+        }
+        let unit = V::Measure::unit();
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let mut check = SplitCheck::new(&unit);
+                for value in values.as_ref() {
+                    check.step(&value.measure(), &mut pred)?;
+                }
+            }
+            Repr::Tree(tree) => tree.check_split(SplitCheck::new(&unit), &mut pred)?,
+        }
+        Ok(self.split(pred))
+    }
+
     /// partial logic from `.split(...)` with only **left** part returned
     pub fn split_left<F>(&self, mut pred: F) -> FingerTree<R, V>
     where
         F: FnMut(&V::Measure) -> bool,
+    {
+        infallible(self.try_split_left(|measure| Ok(pred(measure))))
+    }
+
+    /// partial logic from `.try_split(...)` with only **left** part returned
+    pub fn try_split_left<E, F>(&self, mut pred: F) -> Result<FingerTree<R, V>, E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
     {
         if self.is_empty() {
-            Self::new()
-        } else if pred(&self.measure()) {
-            let (l, _x) = self.rec.split_left(V::Measure::unit(), &mut pred);
-            FingerTree { rec: l }
+            Ok(Self::new())
+        } else if pred(&self.measure())? {
+            if let Repr::Small { values, .. } = &self.repr {
+                let (l, _x, _r) = values.split(V::Measure::unit(), &mut pred)?;
+                return Ok(Self::from_small(l));
+            }
+            let (l, _x) = self.rec().split_left(V::Measure::unit(), &mut pred)?;
+            Ok(FingerTree::from_rec(l))
         } else {
-            self.clone()
+            Ok(self.clone())
         }
     }
 
@@ -239,16 +523,25 @@ where
     where
         F: FnMut(&V::Measure) -> bool,
     {
-        if self.is_empty() {
-            Self::new()
-        } else if pred(&self.measure()) {
-            let (_m, x, r) = self.rec.split_right(V::Measure::unit(), &mut pred);
+        infallible(self.try_split_right(|measure| Ok(pred(measure))))
+    }
 
-            FingerTree {
-                rec: r.push_left(x),
+    /// partial logic from `.try_split(...)` with only **right** part returned
+    pub fn try_split_right<E, F>(&self, mut pred: F) -> Result<FingerTree<R, V>, E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
+    {
+        if self.is_empty() {
+            Ok(Self::new())
+        } else if pred(&self.measure())? {
+            if let Repr::Small { values, .. } = &self.repr {
+                let (l, _x, _r) = values.split(V::Measure::unit(), &mut pred)?;
+                return Ok(Self::from_small(&values.as_ref()[l.len()..]));
             }
+            let (_m, x, r) = self.rec().split_right(V::Measure::unit(), &mut pred)?;
+            Ok(FingerTree::from_rec(r.push_left(x)))
         } else {
-            Self::new()
+            Ok(Self::new())
         }
     }
 
@@ -257,26 +550,806 @@ where
     where
         F: FnMut(&V::Measure) -> bool,
     {
-        if self.is_empty() || !pred(&self.measure()) {
+        infallible(self.try_find(|measure| Ok(pred(measure))))
+    }
+
+    /// Find element for which fallible predicate function `pred` flips from `false`
+    /// to `true`, stops as soon as predicate returns an error, and returns this error
+    pub fn try_find<E, F>(&self, mut pred: F) -> Result<Option<&V>, E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
+    {
+        if self.is_empty() || !pred(&self.measure())? {
+            Ok(None)
+        } else {
+            let (_measure, value) = self.find_prefix(&mut pred)?;
+            Ok(Some(value))
+        }
+    }
+
+    /// Find element for which `pred` flips together with accumulated measure of all
+    /// elements before it, tree must not be empty and `pred` must hold for its measure
+    pub(crate) fn find_prefix<E, F>(&self, pred: &mut F) -> Result<(V::Measure, &V), E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
+    {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let (measure, value) = values.find(V::Measure::unit(), pred)?;
+                Ok((measure, &value.value))
+            }
+            Repr::Tree(tree) => tree.find(V::Measure::unit(), pred),
+        }
+    }
+
+    /// Destructure tree into `k + 1` trees, using `k` provided predicates
+    ///
+    /// Each predicate follows the same rules as predicate passed to
+    /// [`split`](struct.FingerTree.html#method.split) and is called with accumulated
+    /// measure from the beginning of the tree. Predicates must be sorted, that is
+    /// each predicate must flip on the same or later element than previous one.
+    /// Each next split starts from the position of the previous one, and as finger
+    /// tree splits in time logarithmic in distance to the end of the tree this is
+    /// faster than doing separate splits from the root.
+    ///
+    /// Complexity: `O(k ln(N / k))`
+    pub fn split_many<I, F>(&self, preds: I) -> Vec<FingerTree<R, V>>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let mut trees = Vec::new();
+        let mut measure = V::Measure::unit();
+        let mut rest = match &self.repr {
+            Repr::Small { .. } => {
+                // splits of inline trees never allocate
+                let mut rest = self.clone();
+                for mut pred in preds {
+                    let (left, right) = rest.split(|m| pred(&measure.join(m)));
+                    measure = measure.join(&left.measure());
+                    trees.push(left);
+                    rest = right;
+                }
+                trees.push(rest);
+                return trees;
+            }
+            Repr::Tree(tree) => tree.clone(),
+        };
+        for mut pred in preds {
+            if matches!(rest, Tree::Empty) || !pred(&measure.join(&rest.measure())) {
+                trees.push(FingerTree::from_rec(rest));
+                rest = Tree::empty();
+            } else {
+                let (l, x, r) =
+                    infallible(rest.split(measure.clone(), &mut |measure| Ok(pred(measure))));
+                measure = measure.join(&l.measure());
+                trees.push(FingerTree::from_rec(l));
+                rest = r.push_left(x);
+            }
+        }
+        trees.push(FingerTree::from_rec(rest));
+        trees
+    }
+
+    /// Find elements for which each of `k` provided predicates flips from `false` to `true`
+    ///
+    /// Returns one result per predicate, as if [`find`](struct.FingerTree.html#method.find)
+    /// was called with each of them. Predicates must be sorted, in the same sense as for
+    /// [`split_many`](struct.FingerTree.html#method.split_many), which allows to find all
+    /// elements in a single traversal of the tree.
+    ///
+    /// Complexity: `O(k ln(N / k))`
+    pub fn find_many<I, F>(&self, preds: I) -> Vec<Option<&V>>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let mut found = Vec::new();
+        let mut preds = preds.into_iter().peekable();
+        match &self.repr {
+            Repr::Small { measure, values } => {
+                while let Some(pred) = preds.peek_mut() {
+                    if !pred(measure) {
+                        break;
+                    }
+                    let find = values.find(V::Measure::unit(), &mut |measure| Ok(pred(measure)));
+                    found.push(Some(&infallible(find).1.value));
+                    preds.next();
+                }
+            }
+            Repr::Tree(tree) => tree.find_many(&V::Measure::unit(), &mut preds, &mut found),
+        }
+        found.extend(preds.map(|_| None));
+        found
+    }
+
+    /// Find element for which predicate function `pred` flips from `false` to `true`,
+    /// given measures on both sides of the element
+    ///
+    /// Predicate is called with accumulated measure of all elements up to and including
+    /// the current one, and accumulated measure of all elements after it. It must be
+    /// monotonic, that is once it returns `true` for some element it must also return
+    /// `true` for all elements after it. This is `search` operation from the original
+    /// paper, and it is useful when decision depends on both halves of the sequence,
+    /// for example to find weighted median.
+    ///
+    /// Returns `None` if predicate does not hold even for the last element.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn search<F>(&self, mut pred: F) -> Option<&V>
+    where
+        F: FnMut(&V::Measure, &V::Measure) -> bool,
+    {
+        if self.is_empty() || !pred(&self.measure(), &V::Measure::unit()) {
             None
         } else {
-            Some(self.rec.find(V::Measure::unit(), &mut pred))
+            match &self.repr {
+                Repr::Small { values, .. } => {
+                    let (_left, _right, value) =
+                        values.search(V::Measure::unit(), V::Measure::unit(), &mut pred);
+                    Some(&value.value)
+                }
+                Repr::Tree(tree) => {
+                    Some(tree.search(V::Measure::unit(), V::Measure::unit(), &mut pred))
+                }
+            }
+        }
+    }
+
+    /// Accumulated measure of elements in the range defined by two predicates
+    ///
+    /// Range starts with the element for which `start` flips from `false` to `true`
+    /// and ends right before the element for which `end` flips, both predicates accept
+    /// accumulated measure from the beginning of the tree and follow the same rules as
+    /// predicate passed to [`split`](struct.FingerTree.html#method.split). Result is the
+    /// same as `self.split_left(end).split_right(start).measure()`, but no trees are
+    /// constructed, only cached measures of nodes are used. This makes it possible to use
+    /// finger tree as a persistent segment tree.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn measure_range<S, E>(&self, mut start: S, mut end: E) -> V::Measure
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+    {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let mut measure = V::Measure::unit();
+                let mut range = V::Measure::unit();
+                for value in values.as_ref() {
+                    measure = measure.join(&value.measure());
+                    if start(&measure) && !end(&measure) {
+                        range = range.join(&value.measure());
+                    }
+                }
+                range
+            }
+            Repr::Tree(tree) => tree.measure_range(&mut start, &mut end),
+        }
+    }
+
+    /// Accumulated measure of elements in the range of indices
+    ///
+    /// Only available for values which keep track of number of elements in their measure,
+    /// see [`Counted`](measure/trait.Counted.html).
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn measure_range_index<I>(&self, range: I) -> V::Measure
+    where
+        I: RangeBounds<usize>,
+        V: Counted,
+    {
+        let count = |measure: &V::Measure| V::count(measure).unwrap_or(0);
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            // range which ends at `usize::MAX` inclusive is not bounded by any index
+            Bound::Included(end) => end.checked_add(1),
+            Bound::Excluded(end) => Some(*end),
+            Bound::Unbounded => None,
+        };
+        self.measure_range(
+            |measure| count(measure) > start,
+            |measure| end.is_some_and(|end| count(measure) > end),
+        )
+    }
+
+    /// Construct new finger tree with `action` applied to all elements
+    ///
+    /// Action is pushed down to the elements lazily, only once they are accessed.
+    /// Trees based on thread safe references require action to be `Send + Sync`,
+    /// see [`ActionRef`](action/trait.ActionRef.html).
+    ///
+    /// Complexity: `O(1)`
+    pub fn apply_all<A>(&self, action: A) -> Self
+    where
+        A: Action<V>,
+        R::Action: FromAction<V, A>,
+    {
+        match &self.repr {
+            Repr::Small { measure, values } => FingerTree {
+                repr: Repr::Small {
+                    measure: action.act_measure(measure),
+                    values: values.map(|value| {
+                        Inline::from_parts(
+                            action.act(&value.value),
+                            action.act_measure(&value.measure),
+                        )
+                    }),
+                },
+            },
+            Repr::Tree(tree) => FingerTree {
+                repr: Repr::Tree(tree.act(&R::Action::from_action(action))),
+            },
+        }
+    }
+
+    /// Construct new finger tree with `action` applied to the range of elements
+    ///
+    /// Range is defined by `start` and `end` predicates in the same way as in
+    /// [`measure_range`](struct.FingerTree.html#method.measure_range). Only nodes on
+    /// the boundaries of the range are rebuilt, and action is pushed down to the
+    /// elements lazily, only once they are accessed.
+    ///
+    /// Complexity: `O(ln(N))`
+    pub fn apply_range<S, E, A>(&self, mut start: S, mut end: E, action: A) -> Self
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+        A: Action<V>,
+        R::Action: FromAction<V, A>,
+    {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let mut measure = V::Measure::unit();
+                let values = values.map(|value| {
+                    measure = measure.join(&value.measure);
+                    if start(&measure) && !end(&measure) {
+                        Inline::from_parts(
+                            action.act(&value.value),
+                            action.act_measure(&value.measure),
+                        )
+                    } else {
+                        value.clone()
+                    }
+                });
+                FingerTree {
+                    repr: Repr::Small {
+                        measure: values.measure(),
+                        values,
+                    },
+                }
+            }
+            Repr::Tree(tree) => FingerTree {
+                repr: Repr::Tree(tree.act_range(
+                    &V::Measure::unit(),
+                    &mut start,
+                    &mut end,
+                    &R::Action::from_action(action),
+                )),
+            },
+        }
+    }
+
+    /// Replace range of elements with elements from `replacement`
+    ///
+    /// Range is defined by `start` and `end` predicates in the same way as in
+    /// [`measure_range`](struct.FingerTree.html#method.measure_range). Returns a tuple
+    /// with a new tree as its first element and a tree with removed elements as
+    /// its second element. Elements from `replacement` are inserted between two parts
+    /// of the tree while they are concatenated, so small replacement does not require
+    /// construction of intermediate tree.
+    ///
+    /// Complexity: `O(ln(N) + M)`, where `M` is number of elements in `replacement`
+    pub fn splice<S, E, I>(&self, start: S, mut end: E, replacement: I) -> (Self, Self)
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+        I: IntoIterator<Item = V>,
+    {
+        let (left, right) = self.split(start);
+        let offset = left.measure();
+        let (removed, right) = right.split(|measure| end(&offset.join(measure)));
+        // values of inline trees are passed to the middle together with replacement
+        let empty = Tree::empty();
+        let mut middle = NodeBuf::new();
+        let left = left.rec_or_leaves(&empty, &mut middle);
+        let mut right_leaves = NodeBuf::new();
+        let right = right.rec_or_leaves(&empty, &mut right_leaves);
+        let mut replacement = replacement.into_iter().map(Node::leaf);
+        let fits = CONCAT_MIDDLE_MAX - middle.len() - right_leaves.len();
+        for node in replacement.by_ref().take(fits) {
+            middle.push(node);
         }
+        let spliced = match replacement.next() {
+            None => {
+                for node in right_leaves.iter() {
+                    middle.push(node.clone());
+                }
+                Tree::concat(left, &middle, right)
+            }
+            Some(node) => {
+                let mut nodes: Vec<_> = middle.iter().cloned().collect();
+                nodes.push(node);
+                nodes.extend(replacement);
+                nodes.extend(right_leaves.iter().cloned());
+                let middle = tree::build(nodes.as_mut());
+                let left = Tree::concat(left, &NodeBuf::new(), &middle);
+                Tree::concat(&left, &NodeBuf::new(), right)
+            }
+        };
+        (FingerTree::from_rec(spliced), removed)
     }
 
     /// Construct new finger tree which is concatenation of `self` and `other`
     ///
     /// Complexity: `O(ln(N))`
     pub fn concat(&self, other: &Self) -> Self {
-        FingerTree {
-            rec: Tree::concat(&self.rec, &mut ::std::iter::empty(), &other.rec),
+        match (&self.repr, &other.repr) {
+            (Repr::Tree(Tree::Empty), _) => other.clone(),
+            (_, Repr::Tree(Tree::Empty)) => self.clone(),
+            (
+                Repr::Small { measure, values },
+                Repr::Small {
+                    measure: other_measure,
+                    values: other_values,
+                },
+            ) if values.as_ref().len() + other_values.as_ref().len() <= 4 => FingerTree {
+                repr: Repr::Small {
+                    measure: measure.join(other_measure),
+                    values: values + other_values,
+                },
+            },
+            _ => {
+                let empty = Tree::empty();
+                let mut middle = NodeBuf::new();
+                let left = self.rec_or_leaves(&empty, &mut middle);
+                let right = other.rec_or_leaves(&empty, &mut middle);
+                FingerTree::from_rec(Tree::concat(left, &middle, right))
+            }
+        }
+    }
+
+    /// Construct new finger tree which is concatenation of all `trees`
+    ///
+    /// Trees are concatenated pairwise in a balanced way, and elements of small trees
+    /// are passed directly to the middle of concatenation of their larger neighbours,
+    /// instead of folding all trees one by one with
+    /// [`concat`](struct.FingerTree.html#method.concat).
+    ///
+    /// Complexity: `O(K ln(N))`, where `K` is number of trees
+    pub fn concat_all<I>(trees: I) -> Self
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        // large trees with small trees' nodes preceding them
+        let mut parts = Vec::new();
+        let mut middle = NodeBuf::new();
+        for tree in trees {
+            let rec = match tree.repr {
+                Repr::Small { values, .. } => {
+                    let leaves = values.as_ref().iter().map(inline_leaf);
+                    Self::pass_to_middle(&mut parts, &mut middle, leaves);
+                    Tree::Empty
+                }
+                Repr::Tree(rec) => rec,
+            };
+            // nodes of small trees are passed to the middle, one digit at a time
+            match rec {
+                Tree::Empty => (),
+                Tree::Single(node) => Self::pass_to_middle(&mut parts, &mut middle, [node]),
+                // digits wider than the middle are only possible with a large capacity,
+                // such trees are concatenated as a whole instead
+                Tree::Deep(deep)
+                    if matches!(deep.spine(), Tree::Empty)
+                        && deep.left.as_ref().len() <= CONCAT_MIDDLE_MAX
+                        && deep.right.as_ref().len() <= CONCAT_MIDDLE_MAX =>
+                {
+                    for digit in [&deep.left, &deep.right] {
+                        let nodes = digit.as_ref().iter().cloned();
+                        Self::pass_to_middle(&mut parts, &mut middle, nodes);
+                    }
+                }
+                rec => parts.push((mem::take(&mut middle), rec)),
+            }
+        }
+        if !middle.is_empty() {
+            parts.push((middle, Tree::empty()));
+        }
+        while parts.len() > 1 {
+            let mut pairs = parts.into_iter();
+            parts = Vec::new();
+            while let Some((left_middle, left)) = pairs.next() {
+                match pairs.next() {
+                    None => parts.push((left_middle, left)),
+                    Some((middle, right)) => {
+                        parts.push((left_middle, Tree::concat(&left, &middle, &right)))
+                    }
+                }
+            }
+        }
+        match parts.pop() {
+            None => FingerTree::new(),
+            Some((middle, tree)) => {
+                FingerTree::from_rec(Tree::concat(&Tree::empty(), &middle, &tree))
+            }
+        }
+    }
+
+    /// Pass `nodes` to the `middle` of the next concatenation, nodes already in the
+    /// middle are converted to a tree first if all of them would not fit into it
+    #[allow(clippy::type_complexity)]
+    fn pass_to_middle<I>(
+        parts: &mut Vec<(NodeBuf<R, V>, Tree<R, V>)>,
+        middle: &mut NodeBuf<R, V>,
+        nodes: I,
+    ) where
+        I: IntoIterator<Item = Node<R, V>>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let nodes = nodes.into_iter();
+        if middle.len() + nodes.len() > CONCAT_MIDDLE_MAX {
+            let mut flushed: Vec<_> = mem::take(middle).iter().cloned().collect();
+            parts.push((NodeBuf::new(), tree::build(flushed.as_mut())));
+        }
+        for node in nodes {
+            middle.push(node);
+        }
+    }
+
+    /// Construct new finger tree with function `f` applied to all elements
+    ///
+    /// Resulting tree has exactly the same shape as the original one, and measures
+    /// are recomputed bottom-up in the same pass.
+    ///
+    /// Complexity: `O(N)`
+    pub fn map<U, F>(&self, mut f: F) -> FingerTree<R, U>
+    where
+        R: Refs<U>,
+        U: Measured,
+        F: FnMut(&V) -> U,
+    {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                let values = values.map(|value| Inline::from(f(&value.value)));
+                FingerTree {
+                    repr: Repr::Small {
+                        measure: values.measure(),
+                        values,
+                    },
+                }
+            }
+            Repr::Tree(tree) => FingerTree {
+                repr: Repr::Tree(tree.map(&mut f)),
+            },
         }
     }
 
+    /// Construct new finger tree only with elements for which `pred` returns `true`
+    ///
+    /// Resulting tree is balanced, and reuses leafs of the original tree.
+    ///
+    /// Complexity: `O(N)`
+    pub fn filter<F>(&self, mut pred: F) -> Self
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let mut nodes = Vec::new();
+        self.rec().for_each_leaf(&mut |node, value| {
+            if pred(value) {
+                nodes.push(node.clone());
+            }
+        });
+        FingerTree::from_rec(tree::build(nodes.as_mut()))
+    }
+
+    /// Retain only elements for which `pred` returns `true`
+    ///
+    /// Complexity: `O(N)`
+    pub fn retain<F>(&mut self, pred: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        *self = self.filter(pred);
+    }
+
+    /// Construct new finger tree from elements for which `f` returns `Some(..)`
+    ///
+    /// Resulting tree is balanced.
+    ///
+    /// Complexity: `O(N)`
+    pub fn filter_map<U, F>(&self, mut f: F) -> FingerTree<R, U>
+    where
+        R: Refs<U>,
+        U: Measured,
+        F: FnMut(&V) -> Option<U>,
+    {
+        let mut nodes = Vec::new();
+        self.rec().for_each_leaf(&mut |_, value| {
+            if let Some(value) = f(value) {
+                nodes.push(Node::leaf(value));
+            }
+        });
+        FingerTree::from_rec(tree::build(nodes.as_mut()))
+    }
+
+    /// Destructure tree into two trees, first one contains elements for which
+    /// `pred` returns `true` and the second one all other elements
+    ///
+    /// Resulting trees are balanced, and reuse leafs of the original tree.
+    ///
+    /// Complexity: `O(N)`
+    pub fn partition<F>(&self, mut pred: F) -> (Self, Self)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        self.rec().for_each_leaf(&mut |node, value| {
+            if pred(value) {
+                left.push(node.clone());
+            } else {
+                right.push(node.clone());
+            }
+        });
+        (
+            FingerTree::from_rec(tree::build(left.as_mut())),
+            FingerTree::from_rec(tree::build(right.as_mut())),
+        )
+    }
+
     /// Double ended iterator visiting all elements of the tree from left to right
     pub fn iter(&self) -> Iter<R, V> {
         Iter::new(self)
     }
+
+    /// Double ended iterator visiting elements of the tree starting from the element
+    /// for which `pred` flips from `false` to `true`
+    ///
+    /// Predicate follows the same rules as predicate passed to
+    /// [`split`](struct.FingerTree.html#method.split), but no trees are constructed,
+    /// iterator is seeded with subtrees found by descending through cached measures.
+    ///
+    /// Complexity: `O(ln(N))` to construct iterator
+    pub fn iter_from<F>(&self, pred: F) -> Iter<R, V>
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        self.iter_range(pred, |_| false)
+    }
+
+    /// Double ended iterator visiting elements of the tree in the range defined by
+    /// `start` and `end` predicates
+    ///
+    /// Range is defined in the same way as in
+    /// [`measure_range`](struct.FingerTree.html#method.measure_range), iterator is
+    /// seeded with subtrees found by descending through cached measures.
+    ///
+    /// Complexity: `O(ln(N))` to construct iterator
+    pub fn iter_range<S, E>(&self, mut start: S, mut end: E) -> Iter<R, V>
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+    {
+        Iter::range(self, &mut start, &mut end)
+    }
+
+    /// Call `f` with a reference to every element of the tree from left to right
+    ///
+    /// Traverses the tree recursively without cloning elements, which is faster
+    /// than iterating with [`iter`](struct.FingerTree.html#method.iter).
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&V),
+    {
+        match &self.repr {
+            Repr::Small { values, .. } => values.as_ref().iter().for_each(|value| f(value)),
+            Repr::Tree(tree) => tree.for_each_leaf(&mut |_, value| f(value)),
+        }
+    }
+
+    /// Call `f` with a reference to every element of the tree from left to right,
+    /// stopping at the first error which is then returned
+    pub fn try_for_each<E, F>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&V) -> Result<(), E>,
+    {
+        let tree = match &self.repr {
+            Repr::Small { values, .. } => {
+                return values.as_ref().iter().try_for_each(|value| f(value))
+            }
+            Repr::Tree(tree) => tree,
+        };
+        let result = tree.try_fold_leaves((), false, &mut |(), _, value| match f(value) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(error) => ControlFlow::Break(error),
+        });
+        match result {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(error) => Err(error),
+        }
+    }
+
+    /// Iterator over consecutive subtrees, each with accumulated measure bounded by `limit`
+    ///
+    /// Each chunk is the longest prefix of the remaining elements for which `limit`
+    /// predicate has not flipped from `false` to `true`, for example `|m| **m > 50`
+    /// splits tree with [`Size`](measure/struct.Size.html) measure into pages of 50
+    /// elements. If the first remaining element exceeds the limit on its own, it forms
+    /// a chunk by itself.
+    ///
+    /// Complexity: `O(ln(M))` for each chunk, where `M` is size of the chunk
+    pub fn chunks_by_measure<F>(&self, limit: F) -> Chunks<R, V, F>
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        Chunks::new(self, limit)
+    }
+
+    /// Iterator over runs of consecutive elements which are stored together in the tree
+    ///
+    /// Each run contains elements of a single digit or a node of the bottom level, and
+    /// gives access to them by reference, which allows to process elements without
+    /// cloning them and without overhead of traversing the tree for each element.
+    /// Elements are stored in separate leaves, so a run borrows the digit or node which
+    /// holds them rather than a contiguous slice of values.
+    pub fn leaf_runs(&self) -> LeafRuns<'_, R, V> {
+        LeafRuns::new(self)
+    }
+
+    /// Check structural invariants of the tree
+    ///
+    /// Verifies that all elements are stored on the bottom level of nesting, that each
+    /// level of the spine adds one level of nesting, that trees with at most four
+    /// elements are stored inline, and that measures cached in nodes and spine levels
+    /// match measures of their content. Pending actions are not pushed down, so the tree
+    /// is checked as it is stored. Invariants can only be broken by a bug in this crate
+    /// or by measure which is not deterministic or by monoid which does not satisfy
+    /// monoid laws, so this is mostly useful for fuzzing and testing.
+    ///
+    /// Complexity: `O(N)`
+    pub fn check_invariants(&self) -> Result<(), InvariantError<V::Measure>>
+    where
+        V::Measure: PartialEq,
+    {
+        match &self.repr {
+            Repr::Small { measure, values } => {
+                // inline values cache their measure in the same way as leaves
+                let mut computed = V::Measure::unit();
+                for value in values.as_ref() {
+                    let value_measure = value.value.measure();
+                    if value_measure != value.measure {
+                        return Err(InvariantError::NodeMeasure {
+                            depth: 0,
+                            cached: value.measure.clone(),
+                            computed: value_measure,
+                        });
+                    }
+                    computed = computed.join(&value_measure);
+                }
+                if computed == *measure {
+                    Ok(())
+                } else {
+                    Err(InvariantError::TreeMeasure {
+                        depth: 0,
+                        cached: measure.clone(),
+                        computed,
+                    })
+                }
+            }
+            Repr::Tree(tree) => {
+                tree.check(0)?;
+                let mut size = 0;
+                tree.visit(0, &mut |_, depth| {
+                    if depth == 0 {
+                        size += 1;
+                    }
+                });
+                if (1..=4).contains(&size) {
+                    return Err(InvariantError::NotInline { size });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Collect structural statistics of the tree
+    ///
+    /// Complexity: `O(N)`
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            digits: vec![0; R::DIGIT_CAPACITY],
+            ..Stats::default()
+        };
+        match &self.repr {
+            // inline values form a single level with one digit
+            Repr::Small { values, .. } => {
+                stats.depth = 1;
+                stats.nodes.push(values.as_ref().len());
+                stats.digits[values.as_ref().len() - 1] += 1;
+            }
+            Repr::Tree(tree) => tree.stats(0, &mut stats),
+        }
+        stats
+    }
+
+    /// Number of nodes (including leaves) of this tree which are shared with `other` tree
+    ///
+    /// Nodes are compared by address, so this is the number of nodes which do not
+    /// consume any additional memory when both trees are kept alive. Trees with at
+    /// most four elements store them inline, and their elements are only shared with
+    /// the tree itself.
+    ///
+    /// Complexity: `O(N + M)`
+    pub fn shared_nodes(&self, other: &Self) -> usize {
+        let mut nodes = HashSet::new();
+        other.visit_ptrs(&mut |ptr| {
+            nodes.insert(ptr);
+        });
+        let mut shared = 0;
+        self.visit_ptrs(&mut |ptr| {
+            if nodes.contains(&ptr) {
+                shared += 1;
+            }
+        });
+        shared
+    }
+
+    /// Call `f` with addresses of all nodes of the tree, inline values are
+    /// identified by their own address as they are copied with the tree
+    fn visit_ptrs<F>(&self, f: &mut F)
+    where
+        F: FnMut(*const ()),
+    {
+        match &self.repr {
+            Repr::Small { values, .. } => {
+                for value in values.as_ref() {
+                    f(&value.value as *const V as *const ());
+                }
+            }
+            Repr::Tree(tree) => tree.visit(0, &mut |node, _| f(node.as_ptr())),
+        }
+    }
+
+    /// Graphviz DOT description of the structure of the tree
+    ///
+    /// Shows spine levels, digits and nodes of the tree, `label` is called with
+    /// measure of each node and spine level together with the value for leaves,
+    /// and its result is used as a label.
+    pub fn to_dot<F>(&self, label: F) -> String
+    where
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        Self::dot_for_versions(&[self], label)
+    }
+
+    /// Graphviz DOT description of the structure of multiple versions of the tree
+    ///
+    /// Works the same way as [`to_dot`](struct.FingerTree.html#method.to_dot), but
+    /// nodes and spine levels shared between versions are emitted only once with edges
+    /// from each version which references them.
+    pub fn dot_for_versions<F>(versions: &[&Self], label: F) -> String
+    where
+        F: FnMut(&V::Measure, Option<&V>) -> String,
+    {
+        let mut dot = Dot::new(label);
+        for (index, version) in versions.iter().enumerate() {
+            dot.version(index, version);
+        }
+        dot.finish()
+    }
+
+    /// Double ended iterator visiting all elements of the tree together with
+    /// accumulated measure of all elements before them
+    ///
+    /// When iterated from the right side, each element is accompanied with accumulated
+    /// measure of all elements after it instead.
+    pub fn iter_with_prefix(&self) -> PrefixIter<R, V> {
+        PrefixIter::new(self)
+    }
 }
 
 impl<R, V> Measured for FingerTree<R, V>
@@ -287,7 +1360,10 @@ where
     type Measure = V::Measure;
 
     fn measure(&self) -> Self::Measure {
-        self.rec.measure()
+        match &self.repr {
+            Repr::Small { measure, .. } => measure.clone(),
+            Repr::Tree(tree) => tree.measure(),
+        }
     }
 }
 
@@ -369,16 +1445,29 @@ where
     }
 }
 
+impl<R, V> FromIterator<FingerTree<R, V>> for FingerTree<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn from_iter<I: IntoIterator<Item = FingerTree<R, V>>>(iter: I) -> Self {
+        FingerTree::concat_all(iter)
+    }
+}
+
 impl<'a, R, V> From<&'a [V]> for FingerTree<R, V>
 where
     R: Refs<V>,
     V: Measured,
 {
     fn from(vals: &'a [V]) -> Self {
-        let mut nodes: Vec<_> = vals.iter().map(|val| Node::leaf(val.clone())).collect();
-        Self {
-            rec: tree::build(nodes.as_mut()),
+        if vals.len() <= 4 {
+            return vals
+                .iter()
+                .fold(Self::new(), |ft, val| ft.push_right(val.clone()));
         }
+        let mut nodes: Vec<_> = vals.iter().map(|val| Node::leaf(val.clone())).collect();
+        FingerTree::from_rec(tree::build(nodes.as_mut()))
     }
 }
 
@@ -1,5 +1,7 @@
-//! [`Measured`](measure/trait.Measured.html) trait and implementations
+//! [`Measured`](measure/trait.Measured.html) and [`Measurer`](measure/trait.Measurer.html)
+//! traits and implementations
 use std::fmt;
+use std::marker::PhantomData;
 use std::ops::Deref;
 
 use crate::monoid::{Monoid, Sum};
@@ -15,8 +17,22 @@ pub trait Measured: Clone {
 
     /// Associated measure with given value
     fn measure(&self) -> Self::Measure;
+
+    /// Number of elements accounted in the `measure`, if measure keeps track of it
+    ///
+    /// Index based operations are only available for values which override this method.
+    fn count(_measure: &Self::Measure) -> Option<usize> {
+        None
+    }
 }
 
+/// Marker for values whose measure always keeps track of number of elements
+///
+/// Implementing it is a promise that [`Measured::count`](trait.Measured.html#method.count)
+/// never returns `None` and counts elements rather than something else (like total
+/// length of strings), which allows iterators to report exact size.
+pub trait Counted: Measured {}
+
 // impl<T> Measured for T
 // where
 //     T: Deref,
@@ -51,8 +67,14 @@ where
     fn measure(&self) -> Self::Measure {
         Sum(1)
     }
+
+    fn count(measure: &Self::Measure) -> Option<usize> {
+        Some(measure.0)
+    }
 }
 
+impl<T> Counted for Size<T> where T: Clone {}
+
 impl<T> Deref for Size<T> {
     type Target = T;
 
@@ -69,3 +91,171 @@ impl<T: Measured> Measured for &[T] {
             .fold(T::Measure::unit(), |acc, val| acc.join(&val.measure()))
     }
 }
+
+/// Measuring strategy which is external to the value type
+///
+/// Unlike [`Measured`](trait.Measured.html) which ties measure to the value type,
+/// measurer is an object, so the same values can be measured differently in
+/// different trees, and measure can depend on runtime configuration. Closures
+/// `Fn(&V) -> M` are measurers, and [`DefaultMeasurer`](struct.DefaultMeasurer.html)
+/// measures [`Measured`](trait.Measured.html) values with their own measure.
+///
+/// Measurer must be deterministic, as measure of each element is computed only once
+/// when it is inserted into a tree, see [`MeasurerTree`](../struct.MeasurerTree.html).
+pub trait Measurer<V> {
+    /// Measure type
+    type Measure: Monoid + Clone;
+
+    /// Measure of the `value`
+    fn measure(&self, value: &V) -> Self::Measure;
+
+    /// Number of elements accounted in the `measure`, if measure keeps track of it,
+    /// see [`Measured::count`](trait.Measured.html#method.count)
+    fn count(_measure: &Self::Measure) -> Option<usize> {
+        None
+    }
+}
+
+impl<V, M, F> Measurer<V> for F
+where
+    F: Fn(&V) -> M,
+    M: Monoid + Clone,
+{
+    type Measure = M;
+
+    fn measure(&self, value: &V) -> Self::Measure {
+        self(value)
+    }
+}
+
+/// Measurer which uses measure of [`Measured`](trait.Measured.html) values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultMeasurer;
+
+impl<V> Measurer<V> for DefaultMeasurer
+where
+    V: Measured,
+{
+    type Measure = V::Measure;
+
+    fn measure(&self, value: &V) -> Self::Measure {
+        value.measure()
+    }
+
+    fn count(measure: &Self::Measure) -> Option<usize> {
+        V::count(measure)
+    }
+}
+
+/// Value stored together with its measure computed by a [`Measurer`](trait.Measurer.html)
+///
+/// Measurer `M` is only used to determine type of the measure and to count elements,
+/// `WithMeasure::from` measures [`Measured`](trait.Measured.html) values with
+/// [`DefaultMeasurer`](struct.DefaultMeasurer.html), which keeps their count.
+pub struct WithMeasure<V, M>
+where
+    M: Measurer<V>,
+{
+    /// Stored value
+    pub value: V,
+    /// Measure of the value
+    pub measure: M::Measure,
+    measurer: PhantomData<fn() -> M>,
+}
+
+impl<V, M> WithMeasure<V, M>
+where
+    M: Measurer<V>,
+{
+    /// Measure `value` with `measurer`
+    pub fn new(measurer: &M, value: V) -> Self {
+        let measure = measurer.measure(&value);
+        WithMeasure::from_parts(value, measure)
+    }
+
+    /// Wrap `value` with its already computed `measure`
+    pub(crate) fn from_parts(value: V, measure: M::Measure) -> Self {
+        WithMeasure {
+            value,
+            measure,
+            measurer: PhantomData,
+        }
+    }
+}
+
+impl<V> From<V> for WithMeasure<V, DefaultMeasurer>
+where
+    V: Measured,
+{
+    fn from(value: V) -> Self {
+        WithMeasure::new(&DefaultMeasurer, value)
+    }
+}
+
+impl<V, M> Clone for WithMeasure<V, M>
+where
+    V: Clone,
+    M: Measurer<V>,
+{
+    fn clone(&self) -> Self {
+        WithMeasure::from_parts(self.value.clone(), self.measure.clone())
+    }
+}
+
+impl<V, M> PartialEq for WithMeasure<V, M>
+where
+    V: PartialEq,
+    M: Measurer<V>,
+    M::Measure: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.measure == other.measure
+    }
+}
+
+impl<V, M> Eq for WithMeasure<V, M>
+where
+    V: Eq,
+    M: Measurer<V>,
+    M::Measure: Eq,
+{
+}
+
+impl<V, M> fmt::Debug for WithMeasure<V, M>
+where
+    V: fmt::Debug,
+    M: Measurer<V>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<V, M> Measured for WithMeasure<V, M>
+where
+    V: Clone,
+    M: Measurer<V>,
+{
+    type Measure = M::Measure;
+
+    fn measure(&self) -> Self::Measure {
+        self.measure.clone()
+    }
+
+    fn count(measure: &Self::Measure) -> Option<usize> {
+        M::count(measure)
+    }
+}
+
+impl<V> Counted for WithMeasure<V, DefaultMeasurer> where V: Counted {}
+
+impl<V, M> Deref for WithMeasure<V, M>
+where
+    M: Measurer<V>,
+{
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
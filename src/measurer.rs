@@ -0,0 +1,184 @@
+use std::fmt;
+
+use crate::measure::{Measured, Measurer, WithMeasure};
+use crate::reference::Refs;
+use crate::FingerTree;
+
+/// Finger tree parameterized by an external [`Measurer`](measure/trait.Measurer.html)
+///
+/// Elements are stored together with their measure, which is computed by the measurer
+/// once when element is inserted. All trees combined together must use equivalent
+/// measurers, [`concat`](struct.MeasurerTree.html#method.concat) keeps measurer of
+/// the left tree and cached measures of elements of both trees. Underlying finger
+/// tree is available with [`as_tree`](struct.MeasurerTree.html#method.as_tree) for
+/// operations which are not provided by this wrapper.
+///
+/// # Example:
+/// ```
+/// use fingertrees::monoid::Sum;
+/// use fingertrees::{MeasurerTree, RcRefs};
+///
+/// // width of a word in the text layout depends on runtime configured font
+/// let char_width = 7;
+/// let layout: MeasurerTree<RcRefs, _, _> = MeasurerTree::from_values(
+///     move |word: &&str| Sum(word.len() * char_width),
+///     vec!["one", "two", "three"],
+/// );
+/// assert_eq!(layout.measure(), Sum(77));
+/// assert_eq!(layout.find(|width| *width > Sum(30)), Some(&"two"));
+/// ```
+pub struct MeasurerTree<R, V, M>
+where
+    R: Refs<WithMeasure<V, M>>,
+    V: Clone,
+    M: Measurer<V>,
+{
+    tree: FingerTree<R, WithMeasure<V, M>>,
+    measurer: M,
+}
+
+impl<R, V, M> MeasurerTree<R, V, M>
+where
+    R: Refs<WithMeasure<V, M>>,
+    V: Clone,
+    M: Measurer<V> + Clone,
+{
+    /// Construct new empty tree which uses `measurer` to measure its elements
+    pub fn new(measurer: M) -> Self {
+        MeasurerTree {
+            tree: FingerTree::new(),
+            measurer,
+        }
+    }
+
+    /// Construct new tree from `values` measured with `measurer`
+    pub fn from_values<I>(measurer: M, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+    {
+        let tree = values
+            .into_iter()
+            .map(|value| WithMeasure::new(&measurer, value))
+            .collect();
+        MeasurerTree { tree, measurer }
+    }
+
+    fn with_tree(&self, tree: FingerTree<R, WithMeasure<V, M>>) -> Self {
+        MeasurerTree {
+            tree,
+            measurer: self.measurer.clone(),
+        }
+    }
+
+    /// Measurer used by this tree
+    pub fn measurer(&self) -> &M {
+        &self.measurer
+    }
+
+    /// Underlying finger tree of elements stored together with their measures
+    pub fn as_tree(&self) -> &FingerTree<R, WithMeasure<V, M>> {
+        &self.tree
+    }
+
+    /// Accumulated measure of all elements of the tree
+    pub fn measure(&self) -> M::Measure {
+        Measured::measure(&self.tree)
+    }
+
+    /// Returns `true` if tree is empty
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Creates new tree with value prepended to the front of the current tree
+    pub fn push_left(&self, value: V) -> Self {
+        self.with_tree(self.tree.push_left(WithMeasure::new(&self.measurer, value)))
+    }
+
+    /// Creates new tree with value appended to the end of the current tree
+    pub fn push_right(&self, value: V) -> Self {
+        self.with_tree(
+            self.tree
+                .push_right(WithMeasure::new(&self.measurer, value)),
+        )
+    }
+
+    /// Destructure tree into a tuple with first element and the rest of the tree
+    pub fn view_left(&self) -> Option<(V, Self)> {
+        let (head, tail) = self.tree.view_left()?;
+        Some((head.value, self.with_tree(tail)))
+    }
+
+    /// Destructure tree into a tuple with last element and the rest of the tree
+    pub fn view_right(&self) -> Option<(V, Self)> {
+        let (head, tail) = self.tree.view_right()?;
+        Some((head.value, self.with_tree(tail)))
+    }
+
+    /// Destructure tree into two trees, see
+    /// [`FingerTree::split`](struct.FingerTree.html#method.split)
+    pub fn split<F>(&self, pred: F) -> (Self, Self)
+    where
+        F: FnMut(&M::Measure) -> bool,
+    {
+        let (left, right) = self.tree.split(pred);
+        (self.with_tree(left), self.with_tree(right))
+    }
+
+    /// Left part of the [`split`](struct.MeasurerTree.html#method.split)
+    pub fn split_left<F>(&self, pred: F) -> Self
+    where
+        F: FnMut(&M::Measure) -> bool,
+    {
+        self.with_tree(self.tree.split_left(pred))
+    }
+
+    /// Right part of the [`split`](struct.MeasurerTree.html#method.split)
+    pub fn split_right<F>(&self, pred: F) -> Self
+    where
+        F: FnMut(&M::Measure) -> bool,
+    {
+        self.with_tree(self.tree.split_right(pred))
+    }
+
+    /// Find element for which predicate function `pred` flips from `false` to `true`
+    pub fn find<F>(&self, pred: F) -> Option<&V>
+    where
+        F: FnMut(&M::Measure) -> bool,
+    {
+        self.tree.find(pred).map(|entry| &entry.value)
+    }
+
+    /// Construct new tree which is concatenation of `self` and `other`
+    pub fn concat(&self, other: &Self) -> Self {
+        self.with_tree(self.tree.concat(&other.tree))
+    }
+
+    /// Double ended iterator over all elements of the tree
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = V> {
+        self.tree.iter().map(|entry| entry.value)
+    }
+}
+
+impl<R, V, M> Clone for MeasurerTree<R, V, M>
+where
+    R: Refs<WithMeasure<V, M>>,
+    V: Clone,
+    M: Measurer<V> + Clone,
+{
+    fn clone(&self) -> Self {
+        self.with_tree(self.tree.clone())
+    }
+}
+
+impl<R, V, M> fmt::Debug for MeasurerTree<R, V, M>
+where
+    R: Refs<WithMeasure<V, M>>,
+    V: Clone + fmt::Debug,
+    M: Measurer<V> + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MeasurerTree")?;
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
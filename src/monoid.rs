@@ -58,3 +58,33 @@ impl<T> Deref for Sum<T> {
         &self.0
     }
 }
+
+/// Monoid formed by `Ord::max` operation and `None` identity element
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Max<T>(pub Option<T>);
+
+impl<T> Monoid for Max<T>
+where
+    T: Ord + Clone,
+{
+    fn unit() -> Self {
+        Max(None)
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(left), Some(right)) if left >= right => self.clone(),
+            (Some(_), None) => self.clone(),
+            _ => other.clone(),
+        }
+    }
+}
+
+impl<T> Deref for Max<T> {
+    type Target = Option<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -1,30 +1,87 @@
-use std::mem;
+use std::array;
+use std::iter::Peekable;
+use std::ops::ControlFlow;
 
+use crate::action::{ActionRef, LazyCell, PushDown};
+use crate::digit;
+use crate::error::{InvariantError, SplitError};
 use crate::measure::Measured;
 use crate::monoid::Monoid;
 use crate::reference::{Ref, Refs};
+use crate::tree::{RangePiece, SplitCheck};
+use crate::CONCAT_MIDDLE_MAX;
 
 /// Only visible to define custom [`Refs`](trait.Refs.html)
+///
+/// Leaves are a variant of this enum, so every leaf allocation takes the size of
+/// the largest variant. Large values are better stored behind `Rc/Arc`.
 pub enum NodeInner<R, V>
 where
     R: Refs<V>,
     V: Measured,
 {
     #[doc(hidden)]
-    Leaf(V),
+    Leaf { measure: V::Measure, value: V },
     #[doc(hidden)]
     Node2 {
         measure: V::Measure,
-        left: Node<R, V>,
-        right: Node<R, V>,
+        children: [Node<R, V>; 2],
     },
     #[doc(hidden)]
     Node3 {
         measure: V::Measure,
-        left: Node<R, V>,
-        middle: Node<R, V>,
-        right: Node<R, V>,
+        children: [Node<R, V>; 3],
     },
+    /// Lazy or wide branch node, kept out of line so matching on other nodes stays
+    /// as cheap as possible
+    #[doc(hidden)]
+    Boxed {
+        measure: V::Measure,
+        boxed: Box<BoxedNode<R, V>>,
+    },
+}
+
+/// Branch nodes stored out of line by [`NodeInner`](enum.NodeInner.html)
+#[doc(hidden)]
+pub enum BoxedNode<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    /// Branch node with pending action, which is pushed down to its children
+    /// once they are viewed
+    Lazy(LazyNode<R, V>),
+    /// Branch node with more than three children, only used by trees with node
+    /// arity above three
+    Wide(Box<[Node<R, V>]>),
+}
+
+/// Cell holding pending action together with a node, until the node is forced
+type LazyNode<R, V> = <<R as Refs<V>>::Action as ActionRef<V>>::Cell<Node<R, V>>;
+
+/// Borrowed view of a node, lazy nodes are forced before they are viewed
+pub(crate) enum NodeView<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    Leaf(&'a V),
+    /// Children of a branch node, there are at least two of them
+    Branch(&'a [Node<R, V>]),
+}
+
+/// View of a node which does not force lazy nodes, used to inspect structure of
+/// the tree without changing it
+pub(crate) enum NodePeek<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    View(NodeView<'a, R, V>),
+    /// Lazy node with action still pending on the node it wraps
+    Pending(R::Action, Node<R, V>),
+    /// Lazy node which is already forced
+    Forced(&'a Node<R, V>),
 }
 
 pub struct Node<R, V>
@@ -41,8 +98,14 @@ where
     V: Measured,
 {
     pub(crate) fn leaf(value: V) -> Self {
+        let measure = value.measure();
+        Self::leaf_with_measure(value, measure)
+    }
+
+    /// Leaf holding `value` with its already computed `measure`
+    pub(crate) fn leaf_with_measure(value: V, measure: V::Measure) -> Self {
         Node {
-            inner: R::Node::new(NodeInner::Leaf(value)),
+            inner: R::Node::new(NodeInner::Leaf { measure, value }),
         }
     }
 
@@ -51,8 +114,7 @@ where
         Node {
             inner: R::Node::new(NodeInner::Node2 {
                 measure,
-                left,
-                right,
+                children: [left, right],
             }),
         }
     }
@@ -65,54 +127,443 @@ where
         Node {
             inner: R::Node::new(NodeInner::Node3 {
                 measure,
-                left,
-                middle,
-                right,
+                children: [left, middle, right],
             }),
         }
     }
 
-    pub(crate) fn find<F>(&self, measure: V::Measure, pred: &mut F) -> &V
+    fn node_n(children: Box<[Self]>) -> Self {
+        let measure = children.iter().fold(V::Measure::unit(), |measure, child| {
+            measure.join(&child.measure())
+        });
+        Node {
+            inner: R::Node::new(NodeInner::Boxed {
+                measure,
+                boxed: Box::new(BoxedNode::Wide(children)),
+            }),
+        }
+    }
+
+    /// Branch node made of next `len` nodes produced by `nodes`
+    fn branch_from<I>(len: usize, nodes: &mut I) -> Self
     where
-        F: FnMut(&V::Measure) -> bool,
+        I: Iterator<Item = Self>,
     {
-        match self.as_ref() {
-            NodeInner::Leaf(leaf) => leaf,
-            NodeInner::Node2 { left, right, .. } => {
-                let left_measure = measure.join(&left.measure());
-                if pred(&left_measure) {
-                    left.find(measure, pred)
-                } else {
-                    right.find(left_measure, pred)
+        let mut take = || nodes.next().expect("less nodes than expected");
+        match len {
+            2 => Node::node2(take(), take()),
+            3 => Node::node3(take(), take(), take()),
+            _ => Node::node_n((0..len).map(|_| take()).collect()),
+        }
+    }
+
+    /// Branch node with children produced by `f` from `children`, from left to right
+    fn branch_with<T, F>(children: &[T], f: F) -> Self
+    where
+        F: FnMut(&T) -> Self,
+    {
+        Self::branch_from(children.len(), &mut children.iter().map(f))
+    }
+
+    /// Branch node made of `children`
+    pub(crate) fn branch(children: &[Self]) -> Self {
+        match children {
+            [left, right] => Node::node2(left.clone(), right.clone()),
+            [left, middle, right] => Node::node3(left.clone(), middle.clone(), right.clone()),
+            _ => Self::branch_with(children, Node::clone),
+        }
+    }
+
+    fn lazy(action: R::Action, node: Self) -> Self {
+        let measure = action.act_measure(&node.measure());
+        Node {
+            inner: R::Node::new(NodeInner::Boxed {
+                measure,
+                boxed: Box::new(BoxedNode::Lazy(LazyCell::new(action, node))),
+            }),
+        }
+    }
+
+    /// View of the node, lazy nodes are forced so their view is never lazy
+    pub(crate) fn view(&self) -> NodeView<'_, R, V> {
+        match &*self.inner {
+            NodeInner::Leaf { value, .. } => NodeView::Leaf(value),
+            NodeInner::Node2 { children, .. } => NodeView::Branch(children),
+            NodeInner::Node3 { children, .. } => NodeView::Branch(children),
+            NodeInner::Boxed { boxed, .. } => match &**boxed {
+                BoxedNode::Lazy(lazy) => lazy.force().view(),
+                BoxedNode::Wide(children) => NodeView::Branch(children),
+            },
+        }
+    }
+
+    /// View of the node which leaves lazy nodes as they are
+    pub(crate) fn peek(&self) -> NodePeek<'_, R, V> {
+        match &*self.inner {
+            NodeInner::Boxed { boxed, .. } => match &**boxed {
+                BoxedNode::Lazy(lazy) => match lazy.pending() {
+                    Some((action, node)) => NodePeek::Pending(action, node),
+                    // forcing already forced node only returns it
+                    None => NodePeek::Forced(lazy.force()),
+                },
+                BoxedNode::Wide(children) => NodePeek::View(NodeView::Branch(children)),
+            },
+            _ => NodePeek::View(self.view()),
+        }
+    }
+
+    /// Returns `true` for lazy nodes, which only wrap another node
+    pub(crate) fn is_lazy(&self) -> bool {
+        match &*self.inner {
+            NodeInner::Boxed { boxed, .. } => matches!(&**boxed, BoxedNode::Lazy(..)),
+            _ => false,
+        }
+    }
+
+    /// Apply action to all elements of the node
+    ///
+    /// Leafs are updated immediately, branch nodes are wrapped into lazy node which
+    /// pushes action down to its children only once they are viewed.
+    pub(crate) fn act(&self, action: &R::Action) -> Self {
+        match &*self.inner {
+            NodeInner::Leaf { value, .. } => Node::leaf(action.act(value)),
+            NodeInner::Boxed { boxed, .. } => match &**boxed {
+                BoxedNode::Lazy(lazy) => match lazy.pending() {
+                    Some((before, node)) => match action.compose(&before) {
+                        Some(action) => Node::lazy(action, node),
+                        None => Node::lazy(action.clone(), self.clone()),
+                    },
+                    None => Node::lazy(action.clone(), lazy.force().clone()),
+                },
+                BoxedNode::Wide(..) => Node::lazy(action.clone(), self.clone()),
+            },
+            _ => Node::lazy(action.clone(), self.clone()),
+        }
+    }
+
+    /// Apply action to elements of the node which lie inside of the range defined
+    /// by `start` and `end` predicates, `measure` is accumulated measure of all
+    /// elements before this node
+    pub(crate) fn act_range<S, E>(
+        &self,
+        measure: &V::Measure,
+        start: &mut S,
+        end: &mut E,
+        action: &R::Action,
+    ) -> Self
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+    {
+        let next_measure = measure.join(&self.measure());
+        if !start(&next_measure) || end(measure) {
+            // node is completely outside of the range
+            return self.clone();
+        }
+        if start(measure) && !end(&next_measure) {
+            // node is completely inside of the range
+            return self.act(action);
+        }
+        match self.view() {
+            NodeView::Leaf(..) if end(&next_measure) => self.clone(),
+            NodeView::Leaf(..) => self.act(action),
+            NodeView::Branch(children) => {
+                let mut measure = measure.clone();
+                Node::branch_with(children, |child| {
+                    let child_measure = measure.clone();
+                    measure = measure.join(&child.measure());
+                    child.act_range(&child_measure, start, end, action)
+                })
+            }
+        }
+    }
+
+    /// Find element for which predicate flips, returning it together with accumulated
+    /// measure of all elements before it
+    pub(crate) fn find<E, F>(
+        &self,
+        measure: V::Measure,
+        pred: &mut F,
+    ) -> Result<(V::Measure, &V), E>
+    where
+        F: FnMut(&V::Measure) -> Result<bool, E>,
+    {
+        match self.view() {
+            NodeView::Leaf(leaf) => Ok((measure, leaf)),
+            NodeView::Branch(children) => {
+                // predicate is known to flip inside of the node, so it is not
+                // called for the last child
+                let (last, children) = children.split_last().expect("empty branch node");
+                let mut measure = measure;
+                for child in children {
+                    let next_measure = measure.join(&child.measure());
+                    if pred(&next_measure)? {
+                        return child.find(measure, pred);
+                    }
+                    measure = next_measure;
+                }
+                last.find(measure, pred)
+            }
+        }
+    }
+
+    pub(crate) fn search<F>(&self, left: V::Measure, right: V::Measure, pred: &mut F) -> &V
+    where
+        F: FnMut(&V::Measure, &V::Measure) -> bool,
+    {
+        match self.view() {
+            NodeView::Leaf(leaf) => leaf,
+            NodeView::Branch(children) => {
+                let (left, right, child) = digit::search(children, left, right, pred);
+                child.search(left, right, pred)
+            }
+        }
+    }
+
+    /// Construct node of the same shape with `f` applied to all leafs
+    pub(crate) fn map<U, F>(&self, f: &mut F) -> Node<R, U>
+    where
+        R: Refs<U>,
+        U: Measured,
+        F: FnMut(&V) -> U,
+    {
+        match self.view() {
+            NodeView::Leaf(value) => Node::leaf(f(value)),
+            NodeView::Branch(children) => Node::branch_with(children, |child| child.map(f)),
+        }
+    }
+
+    /// Address of the node, which identifies node shared between trees
+    pub(crate) fn as_ptr(&self) -> *const () {
+        &*self.inner as *const NodeInner<R, V> as *const ()
+    }
+
+    /// Call `f` on this node and all its descendants together with their depth,
+    /// where `depth` is depth of this node
+    ///
+    /// Lazy nodes are not forced, node they wrap is visited with the same depth.
+    pub(crate) fn visit<F>(&self, depth: usize, f: &mut F)
+    where
+        F: FnMut(&Node<R, V>, usize),
+    {
+        f(self, depth);
+        match self.peek() {
+            NodePeek::View(NodeView::Leaf(..)) => (),
+            NodePeek::View(NodeView::Branch(children)) => {
+                for child in children {
+                    child.visit(depth.saturating_sub(1), f);
                 }
             }
-            NodeInner::Node3 {
-                left,
-                middle,
-                right,
-                ..
-            } => {
-                let left_measure = measure.join(&left.measure());
-                if pred(&left_measure) {
-                    return left.find(measure, pred);
+            NodePeek::Pending(_, node) => node.visit(depth, f),
+            NodePeek::Forced(node) => node.visit(depth, f),
+        }
+    }
+
+    /// Check that node has expected `depth`, that it has at most `NODE_ARITY` children,
+    /// and that measures cached in it and all its descendants are up to date
+    pub(crate) fn check(&self, depth: usize) -> Result<(), InvariantError<V::Measure>>
+    where
+        V::Measure: PartialEq,
+    {
+        let computed = match self.peek() {
+            // lazy nodes are checked without pushing their action down
+            NodePeek::Pending(action, node) => {
+                node.check(depth)?;
+                action.act_measure(&node.measure())
+            }
+            NodePeek::Forced(node) => {
+                node.check(depth)?;
+                node.measure()
+            }
+            NodePeek::View(NodeView::Leaf(value)) if depth == 0 => value.measure(),
+            NodePeek::View(NodeView::Leaf(..)) => return Err(InvariantError::LeafDepth { depth }),
+            _ if depth == 0 => return Err(InvariantError::BranchDepth),
+            NodePeek::View(NodeView::Branch(children)) if children.len() > R::NODE_ARITY => {
+                return Err(InvariantError::NodeArity {
+                    depth,
+                    arity: children.len(),
+                })
+            }
+            NodePeek::View(NodeView::Branch(children)) => {
+                let mut computed = V::Measure::unit();
+                for child in children {
+                    child.check(depth - 1)?;
+                    computed = computed.join(&child.measure());
                 }
-                let middle_measure = left_measure.join(&middle.measure());
-                if pred(&middle_measure) {
-                    return middle.find(left_measure, pred);
+                computed
+            }
+        };
+        let cached = self.measure();
+        if cached != computed {
+            return Err(InvariantError::NodeMeasure {
+                depth,
+                cached,
+                computed,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fold all leaves of the node from the left side, or from the right side
+    /// if `reverse` is set, stops as soon as `f` returns `ControlFlow::Break`
+    pub(crate) fn try_fold_leaves<A, B, F>(
+        &self,
+        acc: A,
+        reverse: bool,
+        f: &mut F,
+    ) -> ControlFlow<B, A>
+    where
+        F: FnMut(A, &Node<R, V>, &V) -> ControlFlow<B, A>,
+    {
+        match self.view() {
+            NodeView::Leaf(value) => f(acc, self, value),
+            NodeView::Branch([left, right]) => {
+                let (first, second) = if reverse {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                let acc = first.try_fold_leaves(acc, reverse, f)?;
+                second.try_fold_leaves(acc, reverse, f)
+            }
+            NodeView::Branch([left, middle, right]) => {
+                let (first, last) = if reverse {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                let acc = first.try_fold_leaves(acc, reverse, f)?;
+                let acc = middle.try_fold_leaves(acc, reverse, f)?;
+                last.try_fold_leaves(acc, reverse, f)
+            }
+            NodeView::Branch(children) => {
+                let mut acc = acc;
+                if reverse {
+                    for child in children.iter().rev() {
+                        acc = child.try_fold_leaves(acc, reverse, f)?;
+                    }
+                } else {
+                    for child in children {
+                        acc = child.try_fold_leaves(acc, reverse, f)?;
+                    }
                 }
-                right.find(middle_measure, pred)
+                ControlFlow::Continue(acc)
             }
         }
     }
 
-    /// Lift iterator of nodes into iterator of nodes, which are one level deeper
-    ///
-    /// NOTE: will panic on the iterator with less than two elements
-    pub(crate) fn lift<I>(iter: I) -> LiftNodesIter<I::IntoIter, R, V>
+    /// Check that `pred` does not flip back to `false` on boundaries of children visited
+    /// while searching for the element on which it flips, `measure` is accumulated
+    /// measure of all elements before this node
+    pub(crate) fn check_split<F>(
+        &self,
+        measure: &V::Measure,
+        pred: &mut F,
+    ) -> Result<(), SplitError<V::Measure>>
     where
-        I: IntoIterator<Item = Node<R, V>>,
+        F: FnMut(&V::Measure) -> bool,
     {
-        LiftNodesIter::new(iter.into_iter())
+        let children = match self.view() {
+            NodeView::Leaf(..) => return Ok(()),
+            NodeView::Branch(children) => children,
+        };
+        let mut check = SplitCheck::new(measure);
+        let mut found = None;
+        for child in children {
+            if let Some(before) = check.step(&child.measure(), pred)? {
+                found = Some((child, before));
+            }
+        }
+        match found {
+            None => Ok(()),
+            Some((child, before)) => child.check_split(&before, pred),
+        }
+    }
+
+    /// Find elements for all predicates from `preds` which flip inside of this node,
+    /// `measure` is accumulated measure of all elements before this node
+    pub(crate) fn find_many<'a, I, F>(
+        &'a self,
+        measure: &V::Measure,
+        preds: &mut Peekable<I>,
+        found: &mut Vec<Option<&'a V>>,
+    ) where
+        I: Iterator<Item = F>,
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let next_measure = measure.join(&self.measure());
+        while let Some(pred) = preds.peek_mut() {
+            if !pred(&next_measure) {
+                return;
+            }
+            match self.view() {
+                NodeView::Leaf(value) => {
+                    // next predicate might flip on the same element
+                    found.push(Some(value));
+                    preds.next();
+                }
+                NodeView::Branch(children) => {
+                    let mut measure = measure.clone();
+                    for child in children {
+                        child.find_many(&measure, preds, found);
+                        measure = measure.join(&child.measure());
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Call `piece` on all maximal subtrees of this node which lie inside of the range
+    /// defined by `start` and `end` predicates, `measure` is accumulated measure of all
+    /// elements before this node
+    pub(crate) fn range_pieces<'a, S, E, F>(
+        &'a self,
+        measure: &V::Measure,
+        start: &mut S,
+        end: &mut E,
+        piece: &mut F,
+    ) where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+        F: FnMut(RangePiece<'a, R, V>),
+    {
+        let next_measure = measure.join(&self.measure());
+        if !start(&next_measure) || end(measure) {
+            // node is completely outside of the range
+            return;
+        }
+        if start(measure) && !end(&next_measure) {
+            // node is completely inside of the range
+            return piece(RangePiece::Node(self));
+        }
+        match self.view() {
+            NodeView::Leaf(..) => {
+                if !end(&next_measure) {
+                    piece(RangePiece::Node(self))
+                }
+            }
+            NodeView::Branch(children) => {
+                let mut measure = measure.clone();
+                for child in children {
+                    child.range_pieces(&measure, start, end, piece);
+                    measure = measure.join(&child.measure());
+                }
+            }
+        }
+    }
+}
+
+impl<R, V> PushDown<R::Action> for Node<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn push_down(&self, action: &R::Action) -> Self {
+        match self.view() {
+            NodeView::Leaf(value) => Node::leaf(action.act(value)),
+            NodeView::Branch(children) => Node::branch_with(children, |child| child.act(action)),
+        }
     }
 }
 
@@ -136,97 +587,137 @@ where
     type Measure = V::Measure;
 
     fn measure(&self) -> Self::Measure {
-        match self.as_ref() {
-            NodeInner::Leaf(value) => value.measure(),
+        match &*self.inner {
+            NodeInner::Leaf { measure, .. } => measure.clone(),
             NodeInner::Node2 { measure, .. } => measure.clone(),
             NodeInner::Node3 { measure, .. } => measure.clone(),
+            NodeInner::Boxed { measure, .. } => measure.clone(),
         }
     }
 }
 
-impl<R, V> AsRef<NodeInner<R, V>> for Node<R, V>
+/// Capacity of the node buffer, nodes lifted from digits of both trees and from
+/// the middle of concatenation never exceed number of nodes in the top level middle
+const NODE_BUF_CAPACITY: usize = CONCAT_MIDDLE_MAX;
+
+/// Whether nodes with at most `arity` children and digits of at most `capacity` nodes
+/// form a valid tree
+///
+/// At least three children are needed to lift any number of nodes, digits must hold
+/// more nodes than a node so a full digit is not left empty after a node is moved out
+/// of it, and nodes lifted from two full digits and a full buffer must fit the buffer.
+pub(crate) const fn valid_branching(arity: usize, capacity: usize) -> bool {
+    arity >= 3
+        && capacity > arity
+        && (2 * capacity + NODE_BUF_CAPACITY).div_ceil(arity) <= NODE_BUF_CAPACITY
+}
+
+/// Number of nodes combined into the next lifted node when `len` nodes are left,
+/// nodes are as wide as possible unless it leaves a single node behind
+///
+/// NOTE: will panic on a single node
+pub(crate) fn lift_size(len: usize, arity: usize) -> usize {
+    match len {
+        1 => panic!("lift is called on a single node"),
+        len if len <= arity => len,
+        len if len - arity >= 2 => arity,
+        _ => arity - 1,
+    }
+}
+
+/// Fixed capacity buffer of nodes passed to the middle of concatenation, which
+/// lives on the stack so concatenation does not allocate anything except new nodes
+pub(crate) struct NodeBuf<R, V>
 where
     R: Refs<V>,
     V: Measured,
 {
-    fn as_ref(&self) -> &NodeInner<R, V> {
-        &self.inner
-    }
+    nodes: [Option<Node<R, V>>; NODE_BUF_CAPACITY],
+    len: usize,
 }
 
-// Iterator decorator which takes iterator of `Nodes` and make them one level deeper (lift)
-// by combining adjacent nodes. What we want is essentially
-// ```
-// nodes :: [a] -> [Node a]
-// nodes [a, b] = [Node2 a b]
-// nodes [a, b, c] = [Node3 a b c]
-// nodes [a, b, c, d] = [Node2 a b, Node2 c d]
-// nodes (a : b : c : xs) = Node3 a b c : nodes xs
-// ```
-pub(crate) struct LiftNodesIter<I, R, V>
+impl<R, V> NodeBuf<R, V>
 where
-    I: Iterator<Item = Node<R, V>>,
     R: Refs<V>,
     V: Measured,
 {
-    buff: [Option<Node<R, V>>; 5], // look ahead ring buffer
-    index: u8,                     // current index in buffer
-    left: u8,                      // nodes left in buffer
-    iter: I,
+    pub(crate) fn new() -> Self {
+        NodeBuf {
+            nodes: array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append node to the buffer
+    ///
+    /// NOTE: will panic if buffer is full
+    pub(crate) fn push(&mut self, node: Node<R, V>) {
+        assert!(self.len < NODE_BUF_CAPACITY, "node buffer overflow");
+        self.nodes[self.len] = Some(node);
+        self.len += 1;
+    }
+
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = &Node<R, V>> {
+        self.nodes[..self.len].iter().flatten()
+    }
+
+    /// Lift `len` nodes into nodes which are one level deeper, by combining adjacent nodes
+    ///
+    /// What we want with the default node arity of three is essentially
+    /// ```text
+    /// nodes :: [a] -> [Node a]
+    /// nodes [a, b] = [Node2 a b]
+    /// nodes [a, b, c] = [Node3 a b c]
+    /// nodes [a, b, c, d] = [Node2 a b, Node2 c d]
+    /// nodes (a : b : c : xs) = Node3 a b c : nodes xs
+    /// ```
+    /// and wider nodes are combined in the same way, see `lift_size`.
+    ///
+    /// NOTE: will panic on a single node
+    pub(crate) fn lift<'a, I>(len: usize, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Node<R, V>>,
+        R: 'a,
+        V: 'a,
+    {
+        let mut lifted = NodeBuf::new();
+        let mut nodes = nodes.into_iter().cloned();
+        let mut left = len;
+        while left > 0 {
+            let size = lift_size(left, R::NODE_ARITY);
+            lifted.push(Node::branch_from(size, &mut nodes));
+            left -= size;
+        }
+        lifted
+    }
 }
 
-impl<I, R, V> LiftNodesIter<I, R, V>
+impl<R, V> Default for NodeBuf<R, V>
 where
-    I: Iterator<Item = Node<R, V>>,
     R: Refs<V>,
     V: Measured,
 {
-    fn new(mut iter: I) -> Self {
-        let buff = [
-            iter.next(),
-            iter.next(),
-            iter.next(),
-            iter.next(),
-            iter.next(),
-        ];
-        let left = buff.iter().map(|n| if n.is_some() { 1 } else { 0 }).sum();
-        LiftNodesIter {
-            buff,
-            index: 0,
-            left,
-            iter,
-        }
-    }
-
-    fn buff_next(&mut self) -> Node<R, V> {
-        let next = self.iter.next();
-        if next.is_none() {
-            self.left -= 1;
-        }
-        let node = mem::replace(&mut self.buff[self.index as usize], next).unwrap();
-        self.index = (self.index + 1) % 5;
-        node
+    fn default() -> Self {
+        NodeBuf::new()
     }
 }
 
-impl<I, R, V> Iterator for LiftNodesIter<I, R, V>
+/// Value of the leaf node
+pub(crate) fn leaf_value<R, V>(node: &Node<R, V>) -> &V
 where
-    I: Iterator<Item = Node<R, V>>,
     R: Refs<V>,
     V: Measured,
 {
-    type Item = Node<R, V>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.left {
-            0 => None,
-            1 => panic!("Node::lift is called on a single element interator"),
-            2 | 4 => Some(Node::node2(self.buff_next(), self.buff_next())),
-            _ => Some(Node::node3(
-                self.buff_next(),
-                self.buff_next(),
-                self.buff_next(),
-            )),
-        }
+    match node.view() {
+        NodeView::Leaf(value) => value,
+        _ => unreachable!("not leaf returned from top level of the finger-tree"),
     }
 }
@@ -0,0 +1,189 @@
+//! Operations on ordered sequences
+use std::cmp::Ordering;
+
+use super::FingerTree;
+use crate::measure::Measured;
+use crate::node::Node;
+use crate::reference::Refs;
+use crate::tree;
+
+impl<R, V> FingerTree<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    /// Construct new finger tree with elements sorted with comparator function `cmp`
+    ///
+    /// Sort is stable, that is equal elements preserve their order. Resulting tree
+    /// is balanced.
+    ///
+    /// Complexity: `O(N ln(N))`
+    pub fn sort_by<F>(&self, cmp: F) -> Self
+    where
+        F: FnMut(&V, &V) -> Ordering,
+    {
+        let mut values: Vec<_> = self.iter().collect();
+        values.sort_by(cmp);
+        let mut nodes: Vec<_> = values.into_iter().map(Node::leaf).collect();
+        FingerTree::from_rec(tree::build(nodes.as_mut()))
+    }
+
+    /// Construct new finger tree with elements sorted by key extracted with `key` function
+    ///
+    /// Sort is stable, that is equal elements preserve their order. Resulting tree
+    /// is balanced.
+    ///
+    /// Complexity: `O(N ln(N))`
+    pub fn sort_by_key<K, F>(&self, mut key: F) -> Self
+    where
+        K: Ord,
+        F: FnMut(&V) -> K,
+    {
+        self.sort_by(|left, right| key(left).cmp(&key(right)))
+    }
+
+    /// Merge two sorted finger trees into one sorted finger tree
+    ///
+    /// Sequences must be ordered by their measure, that is measure of the prefix of
+    /// the sequence must be equal to the measure of its largest element, for example
+    /// [`Max`](monoid/struct.Max.html) of elements keys. Comparator `cmp` is used to
+    /// compare measures. Merge is stable, equal elements from `left` are placed before
+    /// elements from `right`.
+    ///
+    /// Merge splits one tree by the first element of another and alternates trees
+    /// on each step, as described in the original paper.
+    ///
+    /// Complexity: `O(M ln(N / M))`, where `M` is size of the smaller tree
+    pub fn merge_sorted<C>(left: &Self, right: &Self, mut cmp: C) -> Self
+    where
+        C: FnMut(&V::Measure, &V::Measure) -> Ordering,
+    {
+        let mut merged = FingerTree::new();
+        let mut left = left.clone();
+        let mut right = right.clone();
+        // elements of `left` are placed before equal elements of `right` unless swapped
+        let mut swapped = false;
+        while let Some((head, tail)) = right.view_left() {
+            let head_measure = head.measure();
+            let (before, after) = left.split(|measure| match cmp(measure, &head_measure) {
+                Ordering::Less => false,
+                Ordering::Equal => swapped,
+                Ordering::Greater => true,
+            });
+            merged = (&merged + &before).push_right(head);
+            left = tail;
+            right = after;
+            swapped = !swapped;
+        }
+        &merged + &left
+    }
+}
+
+impl<R, V> FingerTree<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+    V::Measure: Ord,
+{
+    /// Union of two sets represented as ordered finger trees
+    ///
+    /// Both trees must be ordered by their measure, without duplicates, as described in
+    /// [`merge_sorted`](struct.FingerTree.html#method.merge_sorted). If an element is
+    /// present in both trees, element from `self` is used.
+    ///
+    /// Trees are split by each other's elements in turns, so cost of the operation
+    /// depends on how interleaved elements are, and not on the size of the trees.
+    ///
+    /// Complexity: `O(M ln(N / M))`, where `M` is size of the smaller tree
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = FingerTree::new();
+        let mut left = self.clone();
+        let mut right = other.clone();
+        // `left` contains elements from `self` unless swapped
+        let mut swapped = false;
+        while let Some((head, tail)) = right.view_left() {
+            let key = head.measure();
+            let (before, mut rest) = left.split(|measure| *measure >= key);
+            result = &result + &before;
+            let value = match rest.view_left() {
+                Some((value, value_tail)) if value.measure() == key => {
+                    rest = value_tail;
+                    if swapped {
+                        head
+                    } else {
+                        value
+                    }
+                }
+                _ => head,
+            };
+            result = result.push_right(value);
+            left = tail;
+            right = rest;
+            swapped = !swapped;
+        }
+        &result + &left
+    }
+
+    /// Intersection of two sets represented as ordered finger trees
+    ///
+    /// Both trees must be ordered by their measure, without duplicates, as described in
+    /// [`merge_sorted`](struct.FingerTree.html#method.merge_sorted). Elements of the
+    /// result are taken from `self`.
+    ///
+    /// Complexity: `O(M ln(N / M))`, where `M` is size of the smaller tree
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut result = FingerTree::new();
+        let mut left = self.clone();
+        let mut right = other.clone();
+        // `left` contains elements from `self` unless swapped
+        let mut swapped = false;
+        while let Some((head, tail)) = right.view_left() {
+            let key = head.measure();
+            let rest = left.split_right(|measure| *measure >= key);
+            match rest.view_left() {
+                Some((value, value_tail)) if value.measure() == key => {
+                    result = result.push_right(if swapped { head } else { value });
+                    right = value_tail;
+                }
+                _ => right = rest,
+            }
+            left = tail;
+            swapped = !swapped;
+        }
+        result
+    }
+
+    /// Difference of two sets represented as ordered finger trees
+    ///
+    /// Both trees must be ordered by their measure, without duplicates, as described in
+    /// [`merge_sorted`](struct.FingerTree.html#method.merge_sorted). Returns elements
+    /// of `self` which are not present in `other`.
+    ///
+    /// Complexity: `O(M ln(N / M))`, where `M` is size of the smaller tree
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut result = FingerTree::new();
+        let mut left = self.clone();
+        let mut right = other.clone();
+        while let Some((head, tail)) = left.view_left() {
+            let key = head.measure();
+            right = right.split_right(|measure| *measure >= key);
+            let other_key = match right.view_left() {
+                None => break,
+                Some((value, value_tail)) => {
+                    let other_key = value.measure();
+                    if other_key == key {
+                        left = tail;
+                        right = value_tail;
+                        continue;
+                    }
+                    other_key
+                }
+            };
+            // all elements of `left` smaller than `other_key` are not in `other`
+            let (before, rest) = left.split(|measure| *measure >= other_key);
+            result = &result + &before;
+            left = rest;
+        }
+        &result + &left
+    }
+}
@@ -2,6 +2,8 @@ use std::ops::Deref;
 use std::rc::Rc;
 use std::sync::Arc;
 
+use crate::action::ActionRef;
+use crate::digit::WideDigits;
 use crate::measure::Measured;
 use crate::node::NodeInner;
 use crate::tree::TreeInner;
@@ -67,6 +69,28 @@ impl<T> Ref for Arc<T> {
 /// // now you can construct fingertree using your reference type
 /// let _: FingerTree<MyRefs, _> = (0..128).map(Size).collect();
 /// ```
+///
+/// # Branching
+///
+/// References also determine branching of the tree. By default branch nodes have
+/// two or three children and digits hold from one to four nodes. Type families
+/// defined with [`fingertree_define_refs`](macro.fingertree_define_refs.html) accept
+/// node arity `N` and digit capacity `D` as const parameters, wider nodes make the
+/// tree shallower at the cost of larger nodes rebuilt on every update:
+/// ```
+/// use fingertrees::measure::Size;
+/// use fingertrees::{FingerTree, RcRefs};
+///
+/// let ft: FingerTree<RcRefs<8, 9>, _> = (0..1024).map(Size).collect();
+/// assert_eq!(ft.stats().depth, 4);
+/// ```
+/// Invalid branching is rejected once a tree is constructed:
+/// ```compile_fail
+/// use fingertrees::measure::Size;
+/// use fingertrees::{FingerTree, RcRefs};
+///
+/// let ft: FingerTree<RcRefs<2, 3>, _> = (0..1024).map(Size).collect();
+/// ```
 pub trait Refs<V>: Sized
 where
     V: Measured,
@@ -75,33 +99,75 @@ where
     type Node: Ref<Target = NodeInner<Self, V>>;
     /// Reference on a `Tree`
     type Tree: Ref<Target = TreeInner<Self, V>>;
+    /// Reference on a pending action of lazy updates, either
+    /// [`LocalActionRef`](action/type.LocalActionRef.html) or
+    /// [`SyncActionRef`](action/type.SyncActionRef.html)
+    type Action: ActionRef<V>;
+    /// [`DIGIT_CAPACITY`](trait.Refs.html#associatedconstant.DIGIT_CAPACITY) as a type,
+    /// which selects inline storage of digits wider than four nodes
+    type Capacity: WideDigits;
+
+    /// Maximum number of children of a branch node, which must be at least three
+    const NODE_ARITY: usize = 3;
+    /// Maximum number of nodes in a digit, which must be larger than `NODE_ARITY`
+    /// so a full digit leaves room for new nodes after a node is moved out of it,
+    /// and at most `8 * (NODE_ARITY - 1)` so nodes lifted from two full digits during
+    /// concatenation fit into its fixed size buffer, digits of up to 64 nodes
+    /// are supported
+    ///
+    /// Trees with invalid branching fail to compile once they are constructed.
+    const DIGIT_CAPACITY: usize = 4;
 }
 
 /// Helper macro to define custom [`Refs`](trait.Refs.html) for `FingerTree`
+///
+/// Optional last argument names type of pending actions from
+/// [`action`](action/index.html) module, which is
+/// [`SyncActionRef`](action/type.SyncActionRef.html) by default, single threaded
+/// references can use [`LocalActionRef`](action/type.LocalActionRef.html) to accept
+/// actions which are not `Send + Sync`.
+///
+/// Defined type family takes [`NODE_ARITY`](trait.Refs.html#associatedconstant.NODE_ARITY)
+/// and [`DIGIT_CAPACITY`](trait.Refs.html#associatedconstant.DIGIT_CAPACITY) as const
+/// parameters `N` and `D`, which default to `3` and `4`.
 #[macro_export]
 macro_rules! fingertree_define_refs {
     (pub $refs:ident, $ref:ident) => {
-        /// References type family
-        pub enum $refs {}
-        fingertree_define_refs!(@refs_impl $refs, $ref);
+        fingertree_define_refs!(pub $refs, $ref, SyncActionRef);
     };
 
     ($refs:ident, $ref:ident) => {
-        /// References type family
-        enum $refs {}
-        fingertree_define_refs!(@refs_impl $refs, $ref);
+        fingertree_define_refs!($refs, $ref, SyncActionRef);
+    };
+
+    (pub $refs:ident, $ref:ident, $action:ident) => {
+        /// References type family, with node arity `N` and digit capacity `D`
+        pub enum $refs<const N: usize = 3, const D: usize = 4> {}
+        fingertree_define_refs!(@refs_impl $refs, $ref, $action);
+    };
+
+    ($refs:ident, $ref:ident, $action:ident) => {
+        /// References type family, with node arity `N` and digit capacity `D`
+        enum $refs<const N: usize = 3, const D: usize = 4> {}
+        fingertree_define_refs!(@refs_impl $refs, $ref, $action);
     };
 
-    (@refs_impl $refs:ident, $ref:ident) => {
-        impl<V> $crate::Refs<V> for $refs
+    (@refs_impl $refs:ident, $ref:ident, $action:ident) => {
+        impl<V, const N: usize, const D: usize> $crate::Refs<V> for $refs<N, D>
         where
             V: $crate::measure::Measured,
+            $crate::Capacity<D>: $crate::WideDigits,
         {
             type Node = $ref<$crate::NodeInner<Self, V>>;
             type Tree = $ref<$crate::TreeInner<Self, V>>;
+            type Action = $crate::action::$action<V>;
+            type Capacity = $crate::Capacity<D>;
+
+            const NODE_ARITY: usize = N;
+            const DIGIT_CAPACITY: usize = D;
         }
     };
 }
 
-fingertree_define_refs!(pub RcRefs, Rc);
-fingertree_define_refs!(pub ArcRefs, Arc);
+fingertree_define_refs!(pub RcRefs, Rc, LocalActionRef);
+fingertree_define_refs!(pub ArcRefs, Arc, SyncActionRef);
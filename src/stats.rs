@@ -0,0 +1,29 @@
+//! Structural statistics of the finger tree
+//!
+//! See [`stats`](../struct.FingerTree.html#method.stats) and
+//! [`shared_nodes`](../struct.FingerTree.html#method.shared_nodes).
+
+/// Structural statistics of the finger tree
+///
+/// Trees with at most four elements store them inline in a single digit, so they
+/// are reported as a single level with one digit and no nodes other than elements.
+/// Statistics are collected without pushing pending actions down, so they do not
+/// depend on whether pending actions have been applied yet.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of non empty levels of the spine
+    pub depth: usize,
+    /// Number of nodes on each level of nesting, where `nodes[0]` is number of
+    /// elements, `nodes[1]` is number of nodes holding elements and so on
+    pub nodes: Vec<usize>,
+    /// Distribution of digit sizes, where `digits[k - 1]` is number of digits of size `k`,
+    /// with an entry for every size up to digit capacity of the tree
+    pub digits: Vec<usize>,
+}
+
+impl Stats {
+    /// Total number of nodes including leaves
+    pub fn total_nodes(&self) -> usize {
+        self.nodes.iter().sum()
+    }
+}
@@ -1,12 +1,15 @@
 mod quickcheck;
+// marker traits in `sync_send` only exist to be implemented
+#[allow(dead_code)]
 mod simple;
 
 use std::fmt;
 
-use super::FingerTree;
+use super::{FingerTree, Repr};
+use crate::action::ActionRef;
 use crate::measure::Measured;
 use crate::monoid::Monoid;
-use crate::node::{Node, NodeInner};
+use crate::node::{Node, NodePeek, NodeView};
 use crate::reference::Refs;
 use crate::tree::Tree;
 
@@ -20,76 +23,102 @@ where
     V: Measured,
     V::Measure: Eq + PartialEq + fmt::Debug,
 {
-    fn validate_node_rec<R, V>(depth: usize, node: &Node<R, V>)
+    // returns number of elements in the node
+    fn validate_node_rec<R, V>(depth: usize, node: &Node<R, V>) -> usize
     where
         R: Refs<V>,
         V: Measured,
         V::Measure: Eq + PartialEq + fmt::Debug,
     {
-        if depth == 0 {
-            match node.as_ref() {
-                NodeInner::Leaf(..) => (),
-                _ => panic!("all zero depth nodes must be leafs"),
+        // lazy nodes are validated as they are, without forcing them
+        let (size, measure) = match node.peek() {
+            NodePeek::View(NodeView::Leaf(value)) => {
+                assert_eq!(depth, 0, "leaf node with depth: {}", depth);
+                (1, value.measure())
             }
-        } else {
-            match node.as_ref() {
-                NodeInner::Leaf(..) => panic!("leaf node with depth: {}", depth),
-                NodeInner::Node2 {
-                    ref left,
-                    ref right,
-                    ref measure,
-                } => {
-                    validate_node_rec(depth - 1, left);
-                    validate_node_rec(depth - 1, right);
-                    assert_eq!(measure.clone(), left.measure().join(&right.measure()));
-                }
-                NodeInner::Node3 {
-                    ref left,
-                    ref middle,
-                    ref right,
-                    ref measure,
-                } => {
-                    validate_node_rec(depth - 1, left);
-                    validate_node_rec(depth - 1, middle);
-                    validate_node_rec(depth - 1, right);
-                    assert_eq!(
-                        measure.clone(),
-                        left.measure()
-                            .join(&middle.measure())
-                            .join(&right.measure())
-                    );
+            NodePeek::View(NodeView::Branch(children)) => {
+                assert!(depth > 0, "all zero depth nodes must be leafs");
+                assert!(
+                    (2..=R::NODE_ARITY).contains(&children.len()),
+                    "node with {} children",
+                    children.len()
+                );
+                let mut size = 0;
+                let mut measure = V::Measure::unit();
+                for child in children {
+                    size += validate_node_rec(depth - 1, child);
+                    measure = measure.join(&child.measure());
                 }
+                (size, measure)
             }
-        }
+            NodePeek::Pending(action, node) => {
+                assert!(depth > 0, "lazy leaf node");
+                let size = validate_node_rec(depth, &node);
+                (size, action.act_measure(&node.measure()))
+            }
+            NodePeek::Forced(node) => (validate_node_rec(depth, node), node.measure()),
+        };
+        assert_eq!(node.measure(), measure);
+        size
     }
-    fn validate_ft_rec<R, V>(depth: usize, ft: &Tree<R, V>)
+    // returns number of elements in the tree
+    fn validate_ft_rec<R, V>(depth: usize, ft: &Tree<R, V>) -> usize
     where
         R: Refs<V>,
         V: Measured,
         V::Measure: Eq + PartialEq + fmt::Debug,
     {
         match ft {
-            Tree::Empty => (),
+            Tree::Empty => 0,
             Tree::Single(ref node) => validate_node_rec(depth, node),
             Tree::Deep(ref deep) => {
                 let mut m = V::Measure::unit();
+                let mut size = 0;
 
+                assert!(deep.left.as_ref().len() <= R::DIGIT_CAPACITY);
                 for node in deep.left.as_ref() {
-                    validate_node_rec(depth, node);
+                    size += validate_node_rec(depth, node);
                     m = m.join(&node.measure());
                 }
 
-                validate_ft_rec(depth + 1, &deep.spine);
-                m = m.join(&deep.spine.measure());
+                let (action, spine) = deep.peek_spine();
+                size += validate_ft_rec(depth + 1, &spine);
+                m = match action {
+                    Some(action) => m.join(&action.act_measure(&spine.measure())),
+                    None => m.join(&spine.measure()),
+                };
 
+                assert!(deep.right.as_ref().len() <= R::DIGIT_CAPACITY);
                 for node in deep.right.as_ref() {
-                    validate_node_rec(depth, node);
+                    size += validate_node_rec(depth, node);
                     m = m.join(&node.measure());
                 }
 
                 assert_eq!(deep.measure.clone(), m);
+                size
+            }
+        }
+    }
+    match &ft.repr {
+        Repr::Small { measure, values } => {
+            let mut m = V::Measure::unit();
+            for value in values.as_ref() {
+                assert_eq!(value.measure, value.value.measure());
+                m = m.join(&value.measure);
             }
+            assert_eq!(measure.clone(), m);
         }
+        Repr::Tree(tree) => {
+            let size = validate_ft_rec(0, tree);
+            assert!(
+                size == 0 || size > 4,
+                "tree with {} elements is not inline",
+                size
+            );
+        }
+    }
+    // checked tree must agree with the test
+    if let Err(error) = ft.check_invariants() {
+        panic!("{}", error)
     }
-    validate_ft_rec(0, &ft.rec)
 }
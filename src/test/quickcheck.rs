@@ -1,4 +1,5 @@
 use crate::measure::{Measured, Size};
+use crate::reference::ArcRefs;
 use crate::sync::FingerTree;
 use crate::test::validate;
 use quickcheck::{quickcheck, Arbitrary, Gen};
@@ -47,6 +48,18 @@ quickcheck! {
             && left.concat(&right) == ft
     }
 
+    fn split_and_concat_wide(items: Vec<Size<i32>>, index: usize) -> bool {
+        let ft: crate::FingerTree<ArcRefs<4, 6>, _> = items.into_iter().collect();
+        let len = *ft.measure();
+        let index = if len != 0 { index % len } else { 0 };
+        let (left, right) = ft.split(|m| **m > index);
+        validate(&left);
+        validate(&right);
+        *left.measure() == index
+            && *right.measure() == len - index
+            && left.concat(&right) == ft
+    }
+
     fn from_slice(items: Vec<Size<usize>>) -> bool {
         let ft = FingerTree::from(items.as_slice());
         validate(&ft);
@@ -1,5 +1,8 @@
-use crate::measure::{Measured, Size};
-use crate::monoid::Monoid;
+use std::ops::Bound;
+
+use crate::action::Action;
+use crate::measure::{Counted, Measured, Size};
+use crate::monoid::{Max, Monoid, Sum};
 use crate::rc::FingerTree as RcFingerTree;
 use crate::sync::FingerTree as ArcFingerTree;
 use crate::test::validate;
@@ -38,6 +41,32 @@ fn concat() {
     }
 }
 
+#[test]
+fn concat_all() {
+    for step in [1, 3, 7, 20, 64, 200] {
+        let mut start = 0;
+        let mut trees = Vec::new();
+        while start < TEST_SIZE {
+            // vary sizes of the trees, including empty ones
+            let end = std::cmp::min(start + (trees.len() * step) % 97, TEST_SIZE);
+            trees.push((start..end).map(Size).collect::<RcFingerTree<_>>());
+            start = end;
+            if trees.len() % 5 == 0 {
+                start = std::cmp::min(start + step, TEST_SIZE);
+                trees.push((end..start).map(Size).collect());
+            }
+        }
+        let ft = RcFingerTree::concat_all(trees.iter().cloned());
+        validate(&ft);
+        assert!(ft.iter().eq((0..TEST_SIZE).map(Size)));
+
+        let ft: RcFingerTree<Size<usize>> = trees.into_iter().collect();
+        validate(&ft);
+        assert!(ft.iter().eq((0..TEST_SIZE).map(Size)));
+    }
+    assert!(RcFingerTree::<Size<usize>>::concat_all(None).is_empty());
+}
+
 #[test]
 fn split() {
     let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
@@ -51,6 +80,555 @@ fn split() {
     }
 }
 
+#[test]
+fn try_split() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for split in (0..TEST_SIZE).step_by(7) {
+        let pred = |m: &Sum<usize>| Ok::<_, ()>(**m > split);
+        let (left, right) = ft.try_split(pred).unwrap();
+        assert_eq!((left, right), ft.split(|m| **m > split));
+        assert_eq!(ft.try_split_left(pred), Ok(ft.split_left(|m| **m > split)));
+        assert_eq!(
+            ft.try_split_right(pred),
+            Ok(ft.split_right(|m| **m > split))
+        );
+        assert_eq!(ft.try_find(pred), Ok(ft.find(|m| **m > split)));
+
+        // error is propagated on the first call for which predicate fails
+        let mut calls = 0;
+        let failing = |m: &Sum<usize>| {
+            calls += 1;
+            if calls > 3 {
+                Err(calls)
+            } else {
+                Ok(**m > split)
+            }
+        };
+        let result = ft.try_split(failing);
+        assert!(matches!(result, Err(4)) || result == Ok(ft.split(|m| **m > split)));
+    }
+    let overflow = |m: &Sum<usize>| m.checked_mul(usize::MAX).map(|m| m > 0).ok_or("overflow");
+    assert_eq!(ft.try_split(overflow), Err("overflow"));
+    assert_eq!(ft.try_find(overflow), Err("overflow"));
+    let empty = RcFingerTree::<Size<usize>>::new();
+    assert_eq!(empty.try_split_left(overflow), Ok(empty.clone()));
+}
+
+#[test]
+fn split_checked() {
+    use crate::error::SplitError;
+
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for split in 0..TEST_SIZE + 1 {
+        assert_eq!(
+            ft.split_checked(|m| **m > split),
+            Ok(ft.split(|m| **m > split))
+        );
+    }
+    assert_eq!(ft.split_checked(|_| true), Err(SplitError::TrueOnUnit));
+
+    let window = |m: &Sum<usize>| **m >= 3 && **m < TEST_SIZE / 2;
+    match ft.split_checked(window) {
+        Err(SplitError::NonMonotonic { shorter, longer }) => {
+            assert!(window(&shorter));
+            assert!(!window(&longer));
+            assert!(shorter < longer);
+        }
+        result => panic!("non-monotonic predicate was not detected: {:?}", result),
+    }
+    assert!(ft.split_checked(|m| **m % 2 == 1).is_err());
+
+    // violation between two elements stored in the same node deep inside of the spine
+    let (offset, _) = ft
+        .leaf_runs()
+        .scan(0, |offset, run| {
+            *offset += run.len();
+            Some((*offset - run.len(), run.len()))
+        })
+        .find(|&(offset, len)| offset >= TEST_SIZE / 2 && len > 1)
+        .unwrap();
+    let flip = offset + 1;
+    assert_eq!(
+        ft.split_checked(|m| **m >= flip && **m != flip + 1),
+        Err(SplitError::NonMonotonic {
+            shorter: Sum(flip),
+            longer: Sum(flip + 1),
+        })
+    );
+}
+
+#[test]
+fn check_invariants() {
+    use crate::error::InvariantError;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Clone)]
+    struct Shifting(Rc<Cell<usize>>);
+
+    impl Measured for Shifting {
+        type Measure = Sum<usize>;
+
+        fn measure(&self) -> Self::Measure {
+            Sum(self.0.get())
+        }
+    }
+
+    let shift = Rc::new(Cell::new(1));
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(|_| Shifting(shift.clone())).collect();
+    assert_eq!(ft.check_invariants(), Ok(()));
+    shift.set(2);
+    match ft.check_invariants() {
+        Err(InvariantError::NodeMeasure {
+            cached, computed, ..
+        })
+        | Err(InvariantError::TreeMeasure {
+            cached, computed, ..
+        }) => assert_eq!(*computed, 2 * *cached),
+        result => panic!("measure change was not detected: {:?}", result),
+    }
+}
+
+#[test]
+fn check_representation() {
+    use crate::error::InvariantError;
+    use crate::node::Node;
+    use crate::tree::Tree;
+    use crate::Repr;
+
+    // small tree must be stored inline
+    let ft = RcFingerTree {
+        repr: Repr::Tree(Tree::Single(Node::leaf(Size(0)))),
+    };
+    assert_eq!(
+        ft.check_invariants(),
+        Err(InvariantError::NotInline { size: 1 })
+    );
+}
+
+#[test]
+fn inspect_lazy() {
+    use std::cell::Cell;
+
+    thread_local! {
+        static ACTED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Clone)]
+    struct Shift(usize);
+
+    impl Monoid for Shift {
+        fn unit() -> Self {
+            Shift(0)
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            Shift(self.0 + other.0)
+        }
+    }
+
+    impl Action<Size<usize>> for Shift {
+        fn act(&self, value: &Size<usize>) -> Size<usize> {
+            ACTED.with(|acted| acted.set(acted.get() + 1));
+            Size(**value + self.0)
+        }
+
+        fn act_measure(&self, measure: &Sum<usize>) -> Sum<usize> {
+            measure.clone()
+        }
+    }
+
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    let shifted = ft.apply_all(Shift(TEST_SIZE));
+    let acted = ACTED.with(Cell::get);
+    assert!(acted < TEST_SIZE);
+
+    // inspecting the tree does not push action down to the elements
+    validate(&shifted);
+    let stats = shifted.stats();
+    assert_eq!(stats.nodes[0], TEST_SIZE);
+    assert!(shifted.shared_nodes(&ft) > 0);
+    let dot = shifted.to_dot(|m, _| format!("{}", **m));
+    assert_eq!(dot.matches("shape=ellipse").count(), TEST_SIZE);
+    assert_eq!(ACTED.with(Cell::get), acted);
+
+    assert!(shifted.iter().eq((TEST_SIZE..2 * TEST_SIZE).map(Size)));
+    assert_eq!(ACTED.with(Cell::get), TEST_SIZE);
+    assert_eq!(shifted.stats(), stats);
+}
+
+#[test]
+fn stats() {
+    use crate::stats::Stats;
+
+    assert_eq!(RcFingerTree::<Size<usize>>::new().stats().total_nodes(), 0);
+    for size in [1, 2, 7, 100, TEST_SIZE] {
+        let ft: RcFingerTree<_> = (0..size).map(Size).collect();
+        let stats = ft.stats();
+        assert_eq!(stats.nodes[0], size);
+        assert_eq!(stats.nodes.len(), stats.depth);
+        assert!(stats.digits.iter().sum::<usize>() <= 2 * stats.depth);
+        assert_eq!(ft.shared_nodes(&ft), stats.total_nodes());
+
+        let other: RcFingerTree<_> = (0..size).map(Size).collect();
+        assert_eq!(ft.shared_nodes(&other), 0);
+
+        if size <= 4 {
+            // elements are stored inline in a single digit and copied with the tree
+            let mut digits = vec![0; 4];
+            digits[size - 1] = 1;
+            let expected = Stats {
+                depth: 1,
+                nodes: vec![size],
+                digits,
+            };
+            assert_eq!(stats, expected);
+            assert_eq!(ft.shared_nodes(&ft.clone()), 0);
+            assert_eq!(ft.push_right(Size(size)).shared_nodes(&ft), 0);
+            continue;
+        }
+
+        // elements and most of the nodes are shared with derived trees
+        let pushed = ft.push_right(Size(size));
+        assert!(pushed.shared_nodes(&ft) >= size);
+        assert!(pushed.stats().total_nodes() - pushed.shared_nodes(&ft) <= 2 * stats.depth + 1);
+        let (left, _) = ft.split(|m| **m > size / 2);
+        if size / 2 <= 4 {
+            // parts which fit inline copy their elements
+            assert_eq!(left.shared_nodes(&ft), 0);
+        } else {
+            assert!(left.shared_nodes(&ft) >= size / 2);
+        }
+    }
+}
+
+#[test]
+fn branching() {
+    use crate::action::FromAction;
+    use crate::reference::{ArcRefs, RcRefs, Refs};
+    use crate::FingerTree;
+
+    #[derive(Clone)]
+    struct Shift(usize);
+
+    impl Monoid for Shift {
+        fn unit() -> Self {
+            Shift(0)
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            Shift(self.0 + other.0)
+        }
+    }
+
+    impl Action<Size<usize>> for Shift {
+        fn act(&self, value: &Size<usize>) -> Size<usize> {
+            Size(**value + self.0)
+        }
+
+        fn act_measure(&self, measure: &Sum<usize>) -> Sum<usize> {
+            measure.clone()
+        }
+    }
+
+    fn check<R>()
+    where
+        R: Refs<Size<usize>>,
+        R::Action: FromAction<Size<usize>, Shift>,
+    {
+        let ft: FingerTree<R, _> = (0..TEST_SIZE).map(Size).collect();
+        validate(&ft);
+        assert!(ft.iter().eq((0..TEST_SIZE).map(Size)));
+        assert!(ft.iter().rev().eq((0..TEST_SIZE).rev().map(Size)));
+        assert_eq!(ft.stats().digits.len(), R::DIGIT_CAPACITY);
+        let pushed = (0..TEST_SIZE)
+            .rev()
+            .fold(FingerTree::<R, _>::new(), |ft, value| {
+                ft.push_left(Size(value))
+            });
+        validate(&pushed);
+        assert_eq!(ft, pushed);
+
+        let width = std::cmp::max(R::NODE_ARITY, R::DIGIT_CAPACITY);
+        let runs: Vec<_> = ft.leaf_runs().collect();
+        assert!(runs.iter().all(|run| (1..=width).contains(&run.len())));
+        assert!(runs
+            .iter()
+            .flat_map(|run| run.iter())
+            .cloned()
+            .eq(ft.iter()));
+
+        for split in 0..TEST_SIZE {
+            let (left, right) = ft.split(|m| **m > split);
+            validate(&left);
+            validate(&right);
+            assert_eq!(*left.measure(), split);
+            assert_eq!(ft, &left + &right);
+            assert_eq!(ft.find(|m| **m > split), Some(&Size(split)));
+            assert_eq!(
+                ft.search(|left, right| **left > split && **right < TEST_SIZE - split),
+                Some(&Size(split))
+            );
+        }
+
+        for start in (0..TEST_SIZE).step_by(61) {
+            for count in [0, 5, 40] {
+                let end = start + 7;
+                let (spliced, removed) = ft.splice(
+                    |m| **m > start,
+                    |m| **m > end,
+                    (0..count).map(|value| Size(TEST_SIZE + value)),
+                );
+                validate(&spliced);
+                validate(&removed);
+                assert!(spliced.iter().eq((0..start)
+                    .chain(TEST_SIZE..TEST_SIZE + count)
+                    .chain(end.min(TEST_SIZE)..TEST_SIZE)
+                    .map(Size)));
+            }
+        }
+
+        let shifted = ft.apply_range(|m| **m > 100, |m| **m > 300, Shift(TEST_SIZE));
+        validate(&shifted);
+        assert!(shifted.iter().eq((0..TEST_SIZE).map(|value| {
+            Size(if (100..300).contains(&value) {
+                value + TEST_SIZE
+            } else {
+                value
+            })
+        })));
+
+        // small and spine-less trees are passed through the middle of concatenation,
+        // pushing from both ends fills both digits before the spine is used
+        for size in [1, 4, 5, 20, 40] {
+            let trees: Vec<_> = (0..TEST_SIZE)
+                .step_by(size)
+                .map(|start| {
+                    let end = TEST_SIZE.min(start + size);
+                    let middle = (start + end) / 2;
+                    let left = (start..middle)
+                        .rev()
+                        .fold(FingerTree::<R, _>::new(), |ft, value| {
+                            ft.push_left(Size(value))
+                        });
+                    (middle..end).fold(left, |ft, value| ft.push_right(Size(value)))
+                })
+                .collect();
+            let joined = FingerTree::concat_all(trees);
+            validate(&joined);
+            assert_eq!(joined, ft);
+        }
+
+        let mut tail = ft.clone();
+        for expected in 0..TEST_SIZE {
+            let (head, rest) = tail.view_left().expect("tree is drained too early");
+            validate(&rest);
+            assert_eq!(*head, expected);
+            tail = rest;
+        }
+        assert!(tail.is_empty());
+    }
+
+    check::<RcRefs>();
+    check::<RcRefs<4, 5>>();
+    check::<RcRefs<3, 8>>();
+    check::<RcRefs<3, 16>>();
+    check::<RcRefs<8, 9>>();
+    check::<ArcRefs<16, 24>>();
+    check::<RcRefs<16, 64>>();
+
+    // wider nodes make the tree shallower
+    let narrow: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    let wide: FingerTree<RcRefs<8, 9>, _> = (0..TEST_SIZE).map(Size).collect();
+    assert!(wide.stats().depth < narrow.stats().depth);
+}
+
+#[test]
+fn to_dot() {
+    let label = |m: &Sum<usize>, v: Option<&Size<usize>>| match v {
+        Some(v) => format!("\"{}\"", **v),
+        None => format!("{}", **m),
+    };
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    let dot = ft.to_dot(label);
+    assert!(dot.starts_with("digraph"));
+    assert_eq!(dot.matches("shape=ellipse").count(), TEST_SIZE);
+    assert!(dot.contains("label=\"\\\"0\\\"\""));
+
+    // shared nodes are emitted once
+    let (left, right) = ft.split(|m| **m > TEST_SIZE / 2);
+    let pushed = ft.push_right(Size(TEST_SIZE));
+    let dot = RcFingerTree::dot_for_versions(&[&ft, &left, &right, &pushed], label);
+    assert_eq!(dot.matches("shape=doubleoctagon").count(), 4);
+    assert_eq!(dot.matches("shape=ellipse").count(), TEST_SIZE + 1);
+}
+
+#[test]
+fn measurer_tree() {
+    use crate::measure::DefaultMeasurer;
+    use crate::{MeasurerTree, RcRefs};
+
+    let words = vec!["a", "bb", "ccc", "dddd", "eeeee"];
+    // the same values measured by length and by count
+    let by_len = |word: &&str| Sum(word.len());
+    let by_count = |_: &&str| Sum(1);
+    let lens: MeasurerTree<RcRefs, _, _> = MeasurerTree::from_values(by_len, words.clone());
+    let counts: MeasurerTree<RcRefs, _, _> = MeasurerTree::from_values(by_count, words.clone());
+    assert_eq!(lens.measure(), Sum(15));
+    assert_eq!(counts.measure(), Sum(5));
+    assert_eq!(lens.find(|m| **m > 3), Some(&"ccc"));
+    assert_eq!(counts.find(|m| **m > 3), Some(&"dddd"));
+
+    let (left, right) = lens.split(|m| **m > 6);
+    assert!(left.iter().eq(vec!["a", "bb", "ccc"]));
+    assert!(right.iter().eq(vec!["dddd", "eeeee"]));
+    let pushed = left.push_left("ffffff").push_right("g");
+    assert_eq!(pushed.measure(), Sum(13));
+    assert!(pushed
+        .concat(&right)
+        .iter()
+        .rev()
+        .eq(vec!["eeeee", "dddd", "g", "ccc", "bb", "a", "ffffff"]));
+    let (head, tail) = pushed.view_left().unwrap();
+    assert_eq!((head, tail.measure()), ("ffffff", Sum(7)));
+    let (last, init) = pushed.view_right().unwrap();
+    assert_eq!((last, init.measure()), ("g", Sum(12)));
+
+    // `Measured` values work through default measurer
+    let sizes: MeasurerTree<RcRefs, _, _> =
+        MeasurerTree::from_values(DefaultMeasurer, (0..TEST_SIZE).map(Size));
+    assert_eq!(sizes.measure(), Sum(TEST_SIZE));
+    assert_eq!(sizes.split_left(|m| **m > 10).measure(), Sum(10));
+    assert_eq!(
+        sizes.split_right(|m| **m > 10).measure(),
+        Sum(TEST_SIZE - 10)
+    );
+    validate(sizes.as_tree());
+}
+
+#[test]
+fn cached_measure() {
+    use crate::measure::WithMeasure;
+    use std::cell::Cell;
+
+    thread_local! {
+        static MEASURED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Expensive(usize);
+
+    impl Measured for Expensive {
+        type Measure = Sum<usize>;
+
+        fn measure(&self) -> Self::Measure {
+            MEASURED.with(|measured| measured.set(measured.get() + 1));
+            Sum(1)
+        }
+
+        fn count(measure: &Self::Measure) -> Option<usize> {
+            Some(measure.0)
+        }
+    }
+
+    impl Counted for Expensive {}
+
+    let ft: RcFingerTree<_> = (0..TEST_SIZE)
+        .map(|value| WithMeasure::from(Expensive(value)))
+        .collect();
+    for split in 0..TEST_SIZE {
+        let (left, right) = ft.split(|m| **m > split);
+        validate(&left);
+        validate(&right);
+        assert_eq!(ft, &left + &right);
+        assert_eq!(
+            left.push_right(Expensive(0).into()).measure(),
+            Sum(split + 1)
+        );
+    }
+    // each element is measured only once, when it is wrapped
+    assert_eq!(MEASURED.with(Cell::get), 2 * TEST_SIZE);
+    assert_eq!(ft.iter().len(), TEST_SIZE);
+    assert_eq!(ft.find(|m| **m > 10).map(|value| value.0), Some(10));
+
+    // values of inline trees are not measured again when they are split or promoted
+    MEASURED.with(|measured| measured.set(0));
+    let small = (0..4).fold(RcFingerTree::new(), |ft, value| {
+        ft.push_right(Expensive(value))
+    });
+    let (left, right) = small.split(|m| **m > 1);
+    assert_eq!(small.measure_range_index(1..3), Sum(2));
+    let grown = (&left + &right)
+        .push_left(Expensive(4))
+        .push_right(Expensive(5));
+    assert_eq!(grown.iter().len(), 6);
+    assert_eq!(MEASURED.with(Cell::get), 6);
+}
+
+#[test]
+fn chunked() {
+    use crate::{ChunkedFingerTree, RcRefs};
+    type Chunked = ChunkedFingerTree<RcRefs, Size<usize>, 8>;
+
+    let values: Vec<_> = (0..TEST_SIZE).map(Size).collect();
+    let ft: Chunked = values.iter().cloned().collect();
+    assert_eq!(ft, Chunked::from(values.as_slice()));
+    assert!(ft.iter().eq(values.iter()));
+    assert!(ft
+        .as_tree()
+        .iter()
+        .all(|chunk| chunk.len() == 8 || chunk.len() == TEST_SIZE % 8));
+    validate(ft.as_tree());
+
+    for split in 0..TEST_SIZE + 1 {
+        let (left, right) = ft.split(|m| **m > split);
+        validate(left.as_tree());
+        validate(right.as_tree());
+        assert!(left.iter().eq(values[..split].iter()));
+        assert!(right.iter().eq(values[split..].iter()));
+        assert_eq!(left.concat(&right), ft);
+        assert_eq!(ft.find(|m| **m > split), values.get(split));
+    }
+
+    // splitting shares storage of chunks instead of copying values
+    let chunks: Vec<_> = ft.as_tree().iter().map(|chunk| chunk.as_ptr()).collect();
+    for split in [8, 12] {
+        let (left, right) = ft.split(|m| **m > split);
+        let last = left.as_tree().view_right().unwrap().0;
+        let first = right.as_tree().view_left().unwrap().0;
+        assert_eq!(last.as_ptr(), chunks[(split - 1) / 8]);
+        assert_eq!(first.as_ptr(), chunks[split / 8].wrapping_add(split % 8));
+    }
+
+    let mut pushed = Chunked::new();
+    for value in values.iter().cloned() {
+        pushed = pushed.push_right(value);
+    }
+    assert_eq!(pushed, ft);
+    assert_eq!(pushed.as_tree().measure(), Sum(TEST_SIZE));
+    let mut pushed = Chunked::new();
+    for value in values.iter().rev().cloned() {
+        pushed = pushed.push_left(value);
+    }
+    assert_eq!(pushed, ft);
+
+    let mut rest = ft.clone();
+    let mut visited = Vec::new();
+    while let Some((value, tail)) = rest.view_left() {
+        visited.push(value);
+        if let Some((value, init)) = tail.view_right() {
+            visited.push(value);
+            rest = init;
+        } else {
+            rest = tail;
+        }
+    }
+    assert_eq!(visited.len(), TEST_SIZE);
+    let mut collected = Vec::new();
+    ft.for_each(|value| collected.push(value.clone()));
+    assert_eq!(collected, values);
+}
+
 #[test]
 fn split_left() {
     let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
@@ -71,6 +649,78 @@ fn split_right() {
     }
 }
 
+#[test]
+fn split_many() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for step in [1, 3, 37, TEST_SIZE / 2] {
+        let bounds: Vec<_> = (0..TEST_SIZE + step).step_by(step).collect();
+        let trees = ft.split_many(
+            bounds
+                .iter()
+                .map(|bound| move |m: &Sum<usize>| **m > *bound),
+        );
+        assert_eq!(trees.len(), bounds.len() + 1);
+        let mut start = 0;
+        for (tree, bound) in trees.iter().zip(bounds.iter().chain(Some(&TEST_SIZE))) {
+            validate(tree);
+            let end = std::cmp::min(*bound, TEST_SIZE);
+            assert!(tree.iter().eq((start..end).map(Size)));
+            start = std::cmp::max(start, end);
+        }
+        let ft_concat = trees
+            .iter()
+            .fold(RcFingerTree::new(), |acc, tree| &acc + tree);
+        assert_eq!(ft, ft_concat);
+    }
+    let empty = RcFingerTree::<Size<usize>>::new();
+    let trees = empty.split_many([|m: &Sum<usize>| **m > 0]);
+    assert_eq!(trees.len(), 2);
+    assert!(trees.iter().all(|tree| tree.is_empty()));
+}
+
+#[test]
+fn find_many() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for step in [1, 3, 37, TEST_SIZE / 2] {
+        let bounds: Vec<_> = (0..TEST_SIZE + step).step_by(step).chain(Some(0)).collect();
+        let mut bounds_sorted = bounds.clone();
+        bounds_sorted.sort_unstable();
+        let found = ft.find_many(
+            bounds_sorted
+                .iter()
+                .map(|bound| move |m: &Sum<usize>| **m > *bound),
+        );
+        let expected: Vec<_> = bounds_sorted
+            .iter()
+            .map(|bound| ft.find(|m| **m > *bound))
+            .collect();
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn splice() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for start in (0..TEST_SIZE + 1).step_by(37) {
+        for end in (start..TEST_SIZE + 1).step_by(29) {
+            for count in [0, 1, 5, 16, 17, 64] {
+                let (spliced, removed) = ft.splice(
+                    |m| **m > start,
+                    |m| **m > end,
+                    (0..count).map(|value| Size(TEST_SIZE + value)),
+                );
+                validate(&spliced);
+                validate(&removed);
+                assert!(removed.iter().eq((start..end).map(Size)));
+                assert!(spliced.iter().eq((0..start)
+                    .chain(TEST_SIZE..TEST_SIZE + count)
+                    .chain(end..TEST_SIZE)
+                    .map(Size)));
+            }
+        }
+    }
+}
+
 #[test]
 fn reversed() {
     let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
@@ -93,6 +743,213 @@ fn reversed() {
     );
 }
 
+#[test]
+fn iter_with_prefix() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    assert!(ft
+        .iter_with_prefix()
+        .eq((0..TEST_SIZE).map(|index| (Sum(index), Size(index)))));
+    assert!(ft.iter_with_prefix().rev().eq((0..TEST_SIZE)
+        .rev()
+        .map(|index| (Sum(TEST_SIZE - index - 1), Size(index)))));
+
+    let mut iter = ft.iter_with_prefix();
+    assert_eq!(iter.next(), Some((Sum(0), Size(0))));
+    assert_eq!(iter.next_back(), Some((Sum(0), Size(TEST_SIZE - 1))));
+    assert_eq!(iter.next(), Some((Sum(1), Size(1))));
+    assert_eq!(iter.next_back(), Some((Sum(1), Size(TEST_SIZE - 2))));
+
+    // prefix measures are accumulated from measures cached in leaves
+    use std::cell::Cell;
+
+    thread_local! {
+        static MEASURED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Tracked(usize);
+
+    impl Measured for Tracked {
+        type Measure = Sum<usize>;
+
+        fn measure(&self) -> Self::Measure {
+            MEASURED.with(|measured| measured.set(measured.get() + 1));
+            Sum(1)
+        }
+    }
+
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Tracked).collect();
+    MEASURED.with(|measured| measured.set(0));
+    assert!(ft
+        .iter_with_prefix()
+        .eq((0..TEST_SIZE).map(|index| (Sum(index), Tracked(index)))));
+    assert!(ft.iter_with_prefix().rev().eq((0..TEST_SIZE)
+        .rev()
+        .map(|index| (Sum(TEST_SIZE - index - 1), Tracked(index)))));
+    assert_eq!(MEASURED.with(Cell::get), 0);
+}
+
+#[test]
+fn iter_range() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for start in (0..TEST_SIZE + 1).step_by(7) {
+        assert!(ft
+            .iter_from(|m| **m > start)
+            .eq((start..TEST_SIZE).map(Size)));
+        for end in (0..TEST_SIZE + 1).step_by(13) {
+            let iter = ft.iter_range(|m| **m > start, |m| **m > end);
+            assert!(iter.eq((start..std::cmp::max(start, end)).map(Size)));
+            let iter = ft.iter_range(|m| **m > start, |m| **m > end);
+            assert!(iter
+                .rev()
+                .eq((start..std::cmp::max(start, end)).rev().map(Size)));
+        }
+    }
+}
+
+#[test]
+fn iter_fold() {
+    use std::ops::ControlFlow;
+
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    let expected: Vec<_> = (0..TEST_SIZE).map(Size).collect();
+    let folded = ft.iter().fold(Vec::new(), |mut acc, value| {
+        acc.push(value);
+        acc
+    });
+    assert_eq!(folded, expected);
+    let folded = ft.iter().rfold(Vec::new(), |mut acc, value| {
+        acc.push(value);
+        acc
+    });
+    assert!(folded.iter().eq(expected.iter().rev()));
+
+    // fold continues from partially consumed iterator
+    let mut iter = ft.iter();
+    iter.next();
+    iter.next_back();
+    let mut visited = Vec::new();
+    iter.for_each(|value| visited.push(value));
+    assert_eq!(visited, expected[1..TEST_SIZE - 1]);
+    let mut iter = ft.iter();
+    iter.nth(10);
+    assert_eq!(
+        iter.rfold(0, |sum, value| sum + *value),
+        (11..TEST_SIZE).sum::<usize>()
+    );
+
+    // try_fold leaves iterator right after the element it stopped on
+    let mut iter = ft.iter();
+    iter.next();
+    let result = iter.try_fold(0, |sum, value| match *value {
+        100 => Err(sum),
+        _ => Ok(sum + *value),
+    });
+    assert_eq!(result, Err((1..100).sum::<usize>()));
+    assert_eq!(iter.len(), TEST_SIZE - 101);
+    assert_eq!(iter.next(), Some(Size(101)));
+    let result = iter.try_rfold(0, |count, value| (*value > 200).then_some(count + 1));
+    assert_eq!(result, None);
+    assert_eq!(iter.next_back(), Some(Size(199)));
+    assert_eq!(
+        iter.try_fold(0, |sum, value| ControlFlow::<(), _>::Continue(sum + *value)),
+        ControlFlow::Continue((102..199).sum::<usize>())
+    );
+    assert_eq!(iter.next(), None);
+
+    let mut visited = Vec::new();
+    ft.for_each(|value| visited.push(value.clone()));
+    assert_eq!(visited, expected);
+
+    let mut visited = 0;
+    let result = ft.try_for_each(|value| {
+        if **value == 100 {
+            return Err(**value);
+        }
+        visited += 1;
+        Ok(())
+    });
+    assert_eq!(result, Err(100));
+    assert_eq!(visited, 100);
+    assert_eq!(ft.try_for_each(|_| Ok::<_, ()>(())), Ok(()));
+}
+
+#[test]
+fn iter_seek() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for n in (0..TEST_SIZE + 2).step_by(7) {
+        assert_eq!(ft.iter().nth(n), (0..TEST_SIZE).nth(n).map(Size));
+        assert_eq!(ft.iter().nth_back(n), (0..TEST_SIZE).nth_back(n).map(Size));
+
+        let mut iter = ft.iter();
+        let mut expected = 0..TEST_SIZE;
+        while let Some(value) = iter.nth(n) {
+            assert_eq!(Some(*value), expected.nth(n));
+            assert_eq!(iter.next_back().map(|v| *v), expected.next_back());
+            assert_eq!(iter.len(), expected.len());
+        }
+
+        let mut iter = ft.iter();
+        iter.seek(|m| **m > n);
+        assert_eq!(iter.len(), TEST_SIZE.saturating_sub(n));
+        iter.seek_back(|m| **m > n);
+        assert_eq!(iter.len(), TEST_SIZE.saturating_sub(2 * n));
+        assert!(iter.eq((n..TEST_SIZE.saturating_sub(n)).map(Size)));
+    }
+
+    let mut iter = ft.iter_range(|m| **m > 100, |m| **m > 200);
+    assert_eq!(iter.size_hint(), (100, Some(100)));
+    assert_eq!(iter.nth(10), Some(Size(110)));
+    assert_eq!(iter.len(), 89);
+    assert_eq!(ft.iter().count(), TEST_SIZE);
+
+    // measure which does not keep track of count
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(|key| Key(key, 0)).collect();
+    assert_eq!(ft.iter().size_hint(), (0, None));
+    assert_eq!(ft.iter().nth(10), Some(Key(10, 0)));
+    assert_eq!(ft.iter().nth_back(10), Some(Key(TEST_SIZE - 11, 0)));
+    let mut iter = ft.iter();
+    iter.seek(|m| *m >= Max(Some(100)));
+    assert_eq!(iter.next(), Some(Key(100, 0)));
+}
+
+#[test]
+fn chunks_by_measure() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for page in [1, 7, 50, TEST_SIZE, 2 * TEST_SIZE] {
+        let chunks: Vec<_> = ft.chunks_by_measure(|m| **m > page).collect();
+        assert_eq!(chunks.len(), TEST_SIZE.div_ceil(page));
+        for (index, chunk) in chunks.iter().enumerate() {
+            validate(chunk);
+            let start = index * page;
+            let end = std::cmp::min(start + page, TEST_SIZE);
+            assert!(chunk.iter().eq((start..end).map(Size)));
+        }
+    }
+    // element which exceeds limit forms a chunk on its own
+    assert_eq!(ft.chunks_by_measure(|m| **m > 0).count(), TEST_SIZE);
+}
+
+#[test]
+fn leaf_runs() {
+    for size in 0..TEST_SIZE / 4 {
+        let ft: RcFingerTree<_> = (0..size).map(Size).collect();
+        let runs: Vec<_> = ft.leaf_runs().collect();
+        assert!(runs.iter().all(|run| (1..=4).contains(&run.len())));
+        assert!(runs
+            .iter()
+            .flat_map(|run| run.iter())
+            .eq((0..size).map(Size).collect::<Vec<_>>().iter()));
+        assert_eq!(runs.iter().map(|run| *run.measure()).sum::<usize>(), size);
+        if let Some(run) = runs.first() {
+            assert_eq!(run.get(0), Some(&Size(0)));
+            assert_eq!(run.get(run.len()), None);
+            assert_eq!(run.iter().len(), run.len());
+            assert_eq!(run.iter().next_back(), run.get(run.len() - 1));
+        }
+    }
+}
+
 #[test]
 fn find() {
     let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
@@ -102,6 +959,408 @@ fn find() {
     assert!(ft.find(|m| **m > TEST_SIZE).is_none())
 }
 
+#[test]
+fn search() {
+    for size in 0..TEST_SIZE {
+        let ft: RcFingerTree<_> = (0..size).map(Size).collect();
+        // first element for which prefix is larger than suffix
+        let expected = (0..size).find(|index| index + 1 > size - index - 1);
+        assert_eq!(
+            ft.search(|left, right| **left > **right),
+            expected.map(Size).as_ref()
+        );
+    }
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for index in 0..TEST_SIZE {
+        assert_eq!(
+            ft.search(|left, right| **left > index && **right < TEST_SIZE - index),
+            Some(&Size(index))
+        );
+    }
+    assert!(ft.search(|_, right| **right > TEST_SIZE).is_none());
+}
+
+#[test]
+fn measure_range() {
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for start in (0..TEST_SIZE + 1).step_by(7) {
+        for end in (0..TEST_SIZE + 1).step_by(5) {
+            let expected = ft
+                .split_left(|m| **m > end)
+                .split_right(|m| **m > start)
+                .measure();
+            assert_eq!(ft.measure_range(|m| **m > start, |m| **m > end), expected);
+            assert_eq!(ft.measure_range_index(start..end), expected);
+        }
+    }
+    assert_eq!(*ft.measure_range_index(..), TEST_SIZE);
+    assert_eq!(*ft.measure_range_index(3..=7), 5);
+    assert_eq!(*ft.measure_range_index(3..=usize::MAX), TEST_SIZE - 3);
+    assert_eq!(
+        *ft.measure_range_index((Bound::Excluded(usize::MAX), Bound::Unbounded)),
+        0
+    );
+}
+
+#[test]
+fn measure_range_max() {
+    #[derive(Clone, Debug)]
+    struct Value(usize);
+
+    #[derive(Clone, Debug)]
+    struct CountMax(usize, Max<usize>);
+
+    impl Monoid for CountMax {
+        fn unit() -> Self {
+            CountMax(0, Max::unit())
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            CountMax(self.0 + other.0, self.1.join(&other.1))
+        }
+    }
+
+    impl Measured for Value {
+        type Measure = CountMax;
+
+        fn measure(&self) -> Self::Measure {
+            CountMax(1, Max(Some(self.0)))
+        }
+    }
+
+    let values: Vec<_> = (0..TEST_SIZE).map(|i| (i * 7919) % TEST_SIZE).collect();
+    let ft: RcFingerTree<_> = values.iter().cloned().map(Value).collect();
+    for start in (0..TEST_SIZE).step_by(11) {
+        for end in (start..TEST_SIZE + 1).step_by(13) {
+            let CountMax(count, max) = ft.measure_range(|m| m.0 > start, |m| m.0 > end);
+            assert_eq!(count, end - start);
+            assert_eq!(max.0, values[start..end].iter().max().cloned());
+        }
+    }
+}
+
+#[test]
+fn apply_range() {
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Value(i64);
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct CountSum(usize, i64);
+
+    impl Monoid for CountSum {
+        fn unit() -> Self {
+            CountSum(0, 0)
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            CountSum(self.0 + other.0, self.1 + other.1)
+        }
+    }
+
+    impl Measured for Value {
+        type Measure = CountSum;
+
+        fn measure(&self) -> Self::Measure {
+            CountSum(1, self.0)
+        }
+
+        fn count(measure: &Self::Measure) -> Option<usize> {
+            Some(measure.0)
+        }
+    }
+
+    impl Counted for Value {}
+
+    #[derive(Clone)]
+    struct Add(i64);
+
+    impl Monoid for Add {
+        fn unit() -> Self {
+            Add(0)
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            Add(self.0 + other.0)
+        }
+    }
+
+    impl Action<Value> for Add {
+        fn act(&self, value: &Value) -> Value {
+            Value(value.0 + self.0)
+        }
+
+        fn act_measure(&self, measure: &CountSum) -> CountSum {
+            CountSum(measure.0, measure.1 + self.0 * measure.0 as i64)
+        }
+    }
+
+    #[derive(Clone)]
+    struct Mul(i64);
+
+    impl Monoid for Mul {
+        fn unit() -> Self {
+            Mul(1)
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            Mul(self.0 * other.0)
+        }
+    }
+
+    impl Action<Value> for Mul {
+        fn act(&self, value: &Value) -> Value {
+            Value(value.0 * self.0)
+        }
+
+        fn act_measure(&self, measure: &CountSum) -> CountSum {
+            CountSum(measure.0, measure.1 * self.0)
+        }
+    }
+
+    let mut model: Vec<i64> = (0..TEST_SIZE as i64).collect();
+    let init: RcFingerTree<_> = model.iter().cloned().map(Value).collect();
+    let mut ft = init.clone();
+    for step in 0..128 {
+        let start = (step * 37) % TEST_SIZE;
+        let end = (start + step * 13) % (TEST_SIZE + 1);
+        let range = |m: &CountSum| m.0 > start;
+        if step % 3 == 0 {
+            ft = ft.apply_range(range, |m| m.0 > end, Mul(-1));
+            model
+                .iter_mut()
+                .take(end)
+                .skip(start)
+                .for_each(|v| *v = -*v);
+        } else {
+            ft = ft.apply_range(range, |m| m.0 > end, Add(step as i64));
+            model
+                .iter_mut()
+                .take(end)
+                .skip(start)
+                .for_each(|v| *v += step as i64);
+        }
+        validate(&ft);
+        assert_eq!(ft.measure(), CountSum(TEST_SIZE, model.iter().sum()));
+        assert_eq!(
+            ft.measure_range_index(start..),
+            CountSum(TEST_SIZE - start, model[start..].iter().sum())
+        );
+        assert_eq!(
+            ft.find(|m| m.0 > end),
+            model.get(end).cloned().map(Value).as_ref()
+        );
+    }
+    assert_eq!(ft.iter().map(|v| v.0).collect::<Vec<_>>(), model);
+    assert_eq!(
+        ft.iter().rev().map(|v| v.0).collect::<Vec<_>>(),
+        model.iter().rev().cloned().collect::<Vec<_>>()
+    );
+
+    // original tree is not affected
+    validate(&init);
+    assert!(init.iter().map(|v| v.0).eq(0..TEST_SIZE as i64));
+
+    let ft = init.apply_all(Add(1)).apply_all(Mul(2)).apply_all(Add(-2));
+    validate(&ft);
+    assert!(ft
+        .iter()
+        .map(|v| v.0)
+        .eq((0..TEST_SIZE as i64).map(|v| v * 2)));
+}
+
+#[test]
+fn apply_range_lazy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static ACTED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone)]
+    struct Touch;
+
+    impl Monoid for Touch {
+        fn unit() -> Self {
+            Touch
+        }
+
+        fn join(&self, _other: &Self) -> Self {
+            Touch
+        }
+    }
+
+    impl Action<Size<usize>> for Touch {
+        fn act(&self, value: &Size<usize>) -> Size<usize> {
+            ACTED.fetch_add(1, Ordering::SeqCst);
+            value.clone()
+        }
+
+        fn act_measure(&self, measure: &Sum<usize>) -> Sum<usize> {
+            measure.clone()
+        }
+    }
+
+    let (start, end) = (10, TEST_SIZE - 10);
+    let rc: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    let arc: ArcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    let rc = rc.apply_range(|m| **m > start, |m| **m > end, Touch);
+    let arc = arc.apply_range(|m| **m > start, |m| **m > end, Touch);
+    // only leaves on the boundaries of the range are updated immediately
+    assert!(ACTED.load(Ordering::SeqCst) < 64);
+
+    // forced nodes are kept, so each element is updated exactly once
+    for _ in 0..2 {
+        assert!(rc.iter().eq((0..TEST_SIZE).map(Size)));
+        assert!(arc.iter().eq((0..TEST_SIZE).map(Size)));
+    }
+    validate(&rc);
+    validate(&arc);
+    assert_eq!(ACTED.load(Ordering::SeqCst), 2 * (end - start));
+}
+
+#[test]
+fn sort_by() {
+    let values: Vec<_> = (0..TEST_SIZE).map(|i| ((i * 7919) % 101, i)).collect();
+    let ft: RcFingerTree<_> = values.iter().cloned().map(Size).collect();
+
+    // stable sort by the first element only
+    let mut expected = values.clone();
+    expected.sort_by_key(|(key, _)| *key);
+    let sorted = ft.sort_by_key(|value| value.0 .0);
+    validate(&sorted);
+    assert!(sorted
+        .iter()
+        .map(|value| value.0)
+        .eq(expected.iter().cloned()));
+
+    expected.sort_by(|left, right| right.cmp(left));
+    let sorted = ft.sort_by(|left, right| right.cmp(left));
+    validate(&sorted);
+    assert!(sorted
+        .iter()
+        .map(|value| value.0)
+        .eq(expected.iter().cloned()));
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Key(usize, usize);
+
+impl Measured for Key {
+    type Measure = Max<usize>;
+
+    fn measure(&self) -> Self::Measure {
+        Max(Some(self.0))
+    }
+}
+
+#[test]
+fn merge_sorted() {
+    let merge = |left: &[usize], right: &[usize]| {
+        let left: RcFingerTree<_> = left.iter().map(|key| Key(*key, 0)).collect();
+        let right: RcFingerTree<_> = right.iter().map(|key| Key(*key, 1)).collect();
+        let merged = RcFingerTree::merge_sorted(&left, &right, |l, r| l.cmp(r));
+        validate(&merged);
+        let mut expected: Vec<_> = left.iter().chain(&right).collect();
+        expected.sort_by_key(|key| key.0);
+        assert!(merged.iter().eq(expected));
+    };
+    let evens: Vec<_> = (0..TEST_SIZE).map(|i| i * 2).collect();
+    let odds: Vec<_> = (0..TEST_SIZE).map(|i| i * 2 + 1).collect();
+    let dups: Vec<_> = (0..TEST_SIZE).map(|i| i / 7).collect();
+    merge(&evens, &odds);
+    merge(&odds, &evens);
+    merge(&evens, &evens);
+    merge(&dups, &evens);
+    merge(&evens, &dups);
+    merge(&dups, &dups);
+    merge(&evens, &[]);
+    merge(&[], &odds);
+    merge(&evens, &[17, 513, 1000]);
+    merge(&[0, 3, 3, 3, 2000], &dups);
+}
+
+#[test]
+fn set_operations() {
+    use std::collections::BTreeSet;
+
+    let sets: Vec<BTreeSet<usize>> = vec![
+        BTreeSet::new(),
+        (0..TEST_SIZE).map(|i| i * 2).collect(),
+        (0..TEST_SIZE).map(|i| i * 3).collect(),
+        (0..TEST_SIZE).collect(),
+        (TEST_SIZE..TEST_SIZE * 2).collect(),
+        [1, 5, 100, 511, 700].iter().cloned().collect(),
+    ];
+    let tree = |set: &BTreeSet<usize>, tag| -> RcFingerTree<_> {
+        set.iter().map(|key| Key(*key, tag)).collect()
+    };
+    for left in sets.iter() {
+        for right in sets.iter() {
+            let left_ft = tree(left, 0);
+            let right_ft = tree(right, 1);
+
+            let union = left_ft.union(&right_ft);
+            validate(&union);
+            assert!(union.iter().eq(left
+                .union(right)
+                .map(|key| Key(*key, if left.contains(key) { 0 } else { 1 }))));
+
+            let intersection = left_ft.intersection(&right_ft);
+            validate(&intersection);
+            assert!(intersection
+                .iter()
+                .eq(left.intersection(right).map(|key| Key(*key, 0))));
+
+            let difference = left_ft.difference(&right_ft);
+            validate(&difference);
+            assert!(difference
+                .iter()
+                .eq(left.difference(right).map(|key| Key(*key, 0))));
+        }
+    }
+}
+
+#[test]
+fn map_filter() {
+    for size in [0, 1, 2, 5, 9, 17, TEST_SIZE] {
+        let ft: RcFingerTree<_> = (0..size).map(Size).collect();
+
+        let mapped = ft.map(|value| Key(**value * 2, 0));
+        validate(&mapped);
+        assert_eq!(mapped.measure(), Max(size.checked_sub(1).map(|v| v * 2)));
+        assert!(mapped.iter().eq((0..size).map(|v| Key(v * 2, 0))));
+
+        let filtered = ft.filter(|value| **value % 3 == 0);
+        validate(&filtered);
+        assert!(filtered
+            .iter()
+            .eq((0..size).filter(|v| v % 3 == 0).map(Size)));
+
+        let mut retained = ft.clone();
+        retained.retain(|value| **value % 3 != 0);
+        validate(&retained);
+        assert!(retained
+            .iter()
+            .eq((0..size).filter(|v| v % 3 != 0).map(Size)));
+
+        let filtered: RcFingerTree<_> = ft.filter_map(|value| {
+            if **value % 2 == 0 {
+                Some(Key(**value, 1))
+            } else {
+                None
+            }
+        });
+        validate(&filtered);
+        assert!(filtered
+            .iter()
+            .eq((0..size).filter(|v| v % 2 == 0).map(|v| Key(v, 1))));
+
+        let (evens, odds) = ft.partition(|value| **value % 2 == 0);
+        validate(&evens);
+        validate(&odds);
+        assert!(evens.iter().eq((0..size).filter(|v| v % 2 == 0).map(Size)));
+        assert!(odds.iter().eq((0..size).filter(|v| v % 2 != 0).map(Size)));
+    }
+}
+
 #[test]
 fn sync_send() {
     trait TestSend: Send {}
@@ -124,6 +1383,8 @@ fn sync_send() {
     fn is_send<T: Send>() {}
     is_sync::<ArcFingerTree<Size<i32>>>();
     is_send::<ArcFingerTree<Size<i32>>>();
+    is_sync::<crate::ChunkedFingerTree<crate::ArcRefs, Size<i32>, 8>>();
+    is_send::<crate::ChunkedFingerTree<crate::ArcRefs, Size<i32>, 8>>();
 }
 
 #[test]
@@ -138,3 +1399,79 @@ fn from_slice() {
         assert_eq!(one, two);
     }
 }
+
+#[test]
+fn small() {
+    // values of inline trees are copied together with the tree instead of being shared
+    let inline = |ft: &RcFingerTree<Size<usize>>| ft.shared_nodes(&ft.clone()) == 0;
+
+    for size in 0..8 {
+        let vals: Vec<_> = (0..size).map(Size).collect();
+        let ft: RcFingerTree<_> = vals.iter().cloned().collect();
+        validate(&ft);
+        // trees with at most four elements do not allocate any nodes
+        assert_eq!(inline(&ft), size <= 4);
+        assert_eq!(ft, RcFingerTree::from(vals.as_slice()));
+        assert!(ft.iter().rev().eq(vals.iter().rev().cloned()));
+        assert!(ft.leaf_runs().flatten().eq(vals.iter()));
+        assert_eq!(inline(&ft.push_left(Size(size))), size < 4);
+        assert_eq!(inline(&ft.push_right(Size(size))), size < 4);
+
+        let (left, right) = ft.split(|m| **m > size / 2);
+        validate(&left);
+        validate(&right);
+        assert!(left.iter().chain(&right).eq(vals.iter().cloned()));
+        assert!(inline(&left));
+        assert_eq!(ft.find(|m| **m > size / 2), vals.get(size / 2));
+        assert_eq!(&left + &right, ft);
+        assert_eq!(ft.view_right().map(|(v, _)| v), vals.last().cloned());
+
+        let doubled = ft.map(|v| Size(**v * 2));
+        validate(&doubled);
+        assert!(doubled.iter().eq(vals.iter().map(|v| Size(**v * 2))));
+
+        let concat = &ft + &ft;
+        validate(&concat);
+        assert_eq!(inline(&concat), 2 * size <= 4);
+        assert!(concat.iter().eq(vals.iter().chain(&vals).cloned()));
+
+        // range operations on inline trees
+        for start in 0..size + 1 {
+            for end in start..size + 1 {
+                let expected = Sum(end - start);
+                assert_eq!(ft.measure_range(|m| **m > start, |m| **m > end), expected);
+                let range = ft.iter_range(|m| **m > start, |m| **m > end);
+                assert!(range.eq(vals[start..end].iter().cloned()));
+                let (spliced, removed) =
+                    ft.splice(|m| **m > start, |m| **m > end, (0..size).map(Size));
+                validate(&spliced);
+                assert!(removed.iter().eq(vals[start..end].iter().cloned()));
+                assert_eq!(spliced.measure(), Sum(2 * size + start - end));
+                let parts =
+                    ft.split_many([start, end].map(|bound| move |m: &Sum<usize>| **m > bound));
+                assert_eq!(parts.len(), 3);
+                assert!(parts[1].iter().eq(vals[start..end].iter().cloned()));
+                assert!(parts
+                    .iter()
+                    .all(|part| inline(part) == (part.iter().len() <= 4)));
+            }
+        }
+    }
+
+    // trees which shrink to at most four elements are converted back to inline
+    let mut ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    while let Some((_, tail)) = ft.view_left() {
+        validate(&tail);
+        assert_eq!(inline(&tail), tail.iter().len() <= 4);
+        ft = tail;
+    }
+    let ft: RcFingerTree<_> = (0..TEST_SIZE).map(Size).collect();
+    for size in 0..8 {
+        let right = ft.split_right(|m| **m > TEST_SIZE - size);
+        validate(&right);
+        assert_eq!(inline(&right), size <= 4);
+        let removed = ft.splice(|m| **m > 2, |m| **m > TEST_SIZE - size, None).0;
+        validate(&removed);
+        assert_eq!(inline(&removed), size + 2 <= 4);
+    }
+}
@@ -1,9 +1,18 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::iter::Peekable;
+use std::mem;
+use std::ops::ControlFlow;
+
 use self::Tree::{Deep, Empty, Single};
-use crate::digit::Digit;
+use crate::action::{ActionRef, LazyCell, PushDown};
+use crate::digit::{Digit, NodeDigit};
+use crate::error::{InvariantError, SplitError};
 use crate::measure::Measured;
 use crate::monoid::Monoid;
-use crate::node::Node;
+use crate::node::{self, Node, NodeBuf};
 use crate::reference::{Ref, Refs};
+use crate::stats::Stats;
 
 /// Only visible to define custom [`Refs`](trait.Refs.html)
 pub struct TreeInner<R, V>
@@ -12,9 +21,156 @@ where
     V: Measured,
 {
     pub(crate) measure: V::Measure,
-    pub(crate) left: Digit<Node<R, V>>,
-    pub(crate) spine: Tree<R, V>, //TODO: lazy spine
-    pub(crate) right: Digit<Node<R, V>>,
+    pub(crate) left: NodeDigit<R, V>,
+    spine: Spine<R, V>,
+    pub(crate) right: NodeDigit<R, V>,
+}
+
+impl<R, V> TreeInner<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    /// Spine of the tree, pending action is pushed into it on the first access
+    pub(crate) fn spine(&self) -> &Tree<R, V> {
+        match &self.spine {
+            Spine::Tree(tree) => tree,
+            Spine::Lazy(lazy) => lazy.force(),
+        }
+    }
+
+    /// Spine of the tree together with action which is still pending on it, used to
+    /// inspect structure of the tree without forcing the spine
+    pub(crate) fn peek_spine(&self) -> (Option<R::Action>, Cow<'_, Tree<R, V>>) {
+        match &self.spine {
+            Spine::Tree(tree) => (None, Cow::Borrowed(tree)),
+            Spine::Lazy(lazy) => match lazy.pending() {
+                Some((action, tree)) => (Some(action), Cow::Owned(tree)),
+                // forcing already forced spine only returns it
+                None => (None, Cow::Borrowed(lazy.force())),
+            },
+        }
+    }
+}
+
+/// Spine of a deep tree, which might have pending action not yet pushed into it
+enum Spine<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    Tree(Tree<R, V>),
+    Lazy(Box<LazyTree<R, V>>),
+}
+
+/// Cell holding pending action together with a spine, until the spine is forced
+type LazyTree<R, V> = <<R as Refs<V>>::Action as ActionRef<V>>::Cell<Tree<R, V>>;
+
+impl<R, V> Spine<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    /// Apply action to all elements of the spine, deep spines are only wrapped into
+    /// lazy cell, so it takes constant time
+    fn act(&self, action: &R::Action) -> Self {
+        match self {
+            Spine::Tree(tree @ Deep(..)) => {
+                Spine::Lazy(Box::new(LazyCell::new(action.clone(), tree.clone())))
+            }
+            Spine::Tree(tree) => Spine::Tree(tree.act(action)),
+            Spine::Lazy(lazy) => {
+                let (action, tree) = match lazy.pending() {
+                    Some((before, tree)) => match action.compose(&before) {
+                        Some(action) => (action, tree),
+                        None => (action.clone(), lazy.force().clone()),
+                    },
+                    None => (action.clone(), lazy.force().clone()),
+                };
+                Spine::Lazy(Box::new(LazyCell::new(action, tree)))
+            }
+        }
+    }
+}
+
+/// Subtree which lies completely inside of a range
+pub(crate) enum RangePiece<'a, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    Node(&'a Node<R, V>),
+    Tree(&'a Tree<R, V>),
+}
+
+impl<R, V> Clone for RangePiece<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn clone(&self) -> Self {
+        match self {
+            RangePiece::Node(node) => RangePiece::Node(node),
+            RangePiece::Tree(tree) => RangePiece::Tree(tree),
+        }
+    }
+}
+
+impl<R, V> Measured for RangePiece<'_, R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    type Measure = V::Measure;
+
+    fn measure(&self) -> Self::Measure {
+        match self {
+            RangePiece::Node(node) => node.measure(),
+            RangePiece::Tree(tree) => tree.measure(),
+        }
+    }
+}
+
+/// Accumulated measure over consecutive subtrees of one level of the search, which
+/// checks that predicate stays `true` once it flipped
+#[derive(Clone)]
+pub(crate) struct SplitCheck<M> {
+    measure: M,
+    shorter: Option<M>,
+}
+
+impl<M> SplitCheck<M>
+where
+    M: Monoid + Clone,
+{
+    pub(crate) fn new(measure: &M) -> Self {
+        SplitCheck {
+            measure: measure.clone(),
+            shorter: None,
+        }
+    }
+
+    /// Extend accumulated measure with the next subtree, returns measure before the
+    /// subtree if predicate flips on it
+    pub(crate) fn step<F>(&mut self, measure: &M, pred: &mut F) -> Result<Option<M>, SplitError<M>>
+    where
+        F: FnMut(&M) -> bool,
+    {
+        let before = mem::replace(&mut self.measure, M::unit());
+        self.measure = before.join(measure);
+        if pred(&self.measure) {
+            if self.shorter.is_none() {
+                self.shorter = Some(self.measure.clone());
+                return Ok(Some(before));
+            }
+        } else if let Some(shorter) = self.shorter.take() {
+            return Err(SplitError::NonMonotonic {
+                shorter,
+                longer: self.measure.clone(),
+            });
+        }
+        Ok(None)
+    }
 }
 
 pub enum Tree<R, V>
@@ -70,16 +226,18 @@ where
         Tree::Single(node)
     }
 
-    pub(crate) fn deep(
-        left: Digit<Node<R, V>>,
-        spine: Tree<R, V>,
-        right: Digit<Node<R, V>>,
-    ) -> Self {
+    pub(crate) fn deep(left: NodeDigit<R, V>, spine: Tree<R, V>, right: NodeDigit<R, V>) -> Self {
+        const {
+            assert!(
+                node::valid_branching(R::NODE_ARITY, R::DIGIT_CAPACITY),
+                "invalid branching, see Refs::NODE_ARITY and Refs::DIGIT_CAPACITY",
+            )
+        };
         let measure = left.measure().join(&spine.measure()).join(&right.measure());
         Tree::Deep(R::Tree::new(TreeInner {
             measure,
             left,
-            spine,
+            spine: Spine::Tree(spine),
             right,
         }))
     }
@@ -93,17 +251,19 @@ where
                 Digit::One([other.clone()]),
             ),
             Deep(deep) => {
-                if let [l0, l1, l2, l3] = deep.left.as_ref() {
+                let left = deep.left.as_ref();
+                if left.len() == R::DIGIT_CAPACITY {
+                    // inner nodes of the full digit are moved to the spine
+                    let (left, inner) = left.split_at(R::DIGIT_CAPACITY - R::NODE_ARITY);
                     Self::deep(
-                        Digit::Two([value, l0.clone()]),
-                        deep.spine
-                            .push_left(Node::node3(l1.clone(), l2.clone(), l3.clone())),
+                        Digit::concat(&[value], left),
+                        deep.spine().push_left(Node::branch(inner)),
                         deep.right.clone(),
                     )
                 } else {
                     Self::deep(
                         &Digit::One([value]) + &deep.left,
-                        deep.spine.clone(),
+                        deep.spine().clone(),
                         deep.right.clone(),
                     )
                 }
@@ -120,18 +280,20 @@ where
                 Digit::One([value]),
             ),
             Deep(deep) => {
-                if let [r0, r1, r2, r3] = deep.right.as_ref() {
+                let right = deep.right.as_ref();
+                if right.len() == R::DIGIT_CAPACITY {
+                    // inner nodes of the full digit are moved to the spine
+                    let (inner, right) = right.split_at(R::NODE_ARITY);
                     Self::deep(
                         deep.left.clone(),
-                        deep.spine
-                            .push_right(Node::node3(r0.clone(), r1.clone(), r2.clone())),
-                        Digit::Two([r3.clone(), value]),
+                        deep.spine().push_right(Node::branch(inner)),
+                        Digit::concat(right, &[value]),
                     )
                 } else {
                     Self::deep(
                         deep.left.clone(),
-                        deep.spine.clone(),
-                        &deep.right + Digit::One([value]),
+                        deep.spine().clone(),
+                        &deep.right + [value],
                     )
                 }
             }
@@ -140,7 +302,7 @@ where
 
     // left element is not `Digit` because `Digit` cannot be empty, but left in current
     // position can be.
-    fn deep_left(left: &[Node<R, V>], spine: &Tree<R, V>, right: &Digit<Node<R, V>>) -> Self {
+    fn deep_left(left: &[Node<R, V>], spine: &Tree<R, V>, right: &NodeDigit<R, V>) -> Self {
         if left.is_empty() {
             match spine.view_left() {
                 Some((head, tail)) => Self::deep((&head).into(), tail, right.clone()),
@@ -159,13 +321,13 @@ where
                 None => unreachable!("digit cannot be empty"),
                 Some((head, tail)) => Some((
                     head.clone(),
-                    Self::deep_left(tail, &deep.spine, &deep.right),
+                    Self::deep_left(tail, deep.spine(), &deep.right),
                 )),
             },
         }
     }
 
-    fn deep_right(left: &Digit<Node<R, V>>, spine: &Tree<R, V>, right: &[Node<R, V>]) -> Self {
+    fn deep_right(left: &NodeDigit<R, V>, spine: &Tree<R, V>, right: &[Node<R, V>]) -> Self {
         if right.is_empty() {
             match spine.view_right() {
                 Some((head, tail)) => Self::deep(left.clone(), tail, (&head).into()),
@@ -184,174 +346,176 @@ where
                 None => unreachable!("digit cannot be empty"),
                 Some((head, tail)) => Some((
                     head.clone(),
-                    Self::deep_right(&deep.left, &deep.spine, tail),
+                    Self::deep_right(&deep.left, deep.spine(), tail),
                 )),
             },
         }
     }
 
-    pub(crate) fn split<F>(
+    pub(crate) fn split<E, F>(
         &self,
         measure: V::Measure,
         pred: &mut F,
-    ) -> (Tree<R, V>, Node<R, V>, Tree<R, V>)
+    ) -> Result<(Self, Node<R, V>, Self), E>
     where
-        F: FnMut(&V::Measure) -> bool,
+        F: FnMut(&V::Measure) -> Result<bool, E>,
     {
         match self {
             Empty => unreachable!("recursive split of finger-tree called on empty tree"),
-            Single(value) => (Tree::empty(), value.clone(), Tree::empty()),
+            Single(value) => Ok((Tree::empty(), value.clone(), Tree::empty())),
             Deep(deep) => {
                 // left
                 let left_measure = measure.join(&deep.left.measure());
-                if pred(&left_measure) {
-                    let (l, x, r) = deep.left.split(measure, pred);
-                    return (
+                if pred(&left_measure)? {
+                    let (l, x, r) = deep.left.split(measure, pred)?;
+                    return Ok((
                         Tree::from(l),
                         x.clone(),
-                        Self::deep_left(r, &deep.spine, &deep.right),
-                    );
+                        Self::deep_left(r, deep.spine(), &deep.right),
+                    ));
                 }
                 // spine
-                let spine_measure = left_measure.join(&deep.spine.measure());
-                if pred(&spine_measure) {
-                    let (sl, sx, sr) = deep.spine.split(left_measure.clone(), pred);
+                let spine_measure = left_measure.join(&deep.spine().measure());
+                if pred(&spine_measure)? {
+                    let (sl, sx, sr) = deep.spine().split(left_measure.clone(), pred)?;
                     let sx = Digit::from(&sx);
-                    let (l, x, r) = sx.split(left_measure.join(&sl.measure()), pred);
-                    return (
+                    let (l, x, r) = sx.split(left_measure.join(&sl.measure()), pred)?;
+                    return Ok((
                         Self::deep_right(&deep.left, &sl, l),
                         x.clone(),
                         Self::deep_left(r, &sr, &deep.right),
-                    );
+                    ));
                 }
                 // right
-                let (l, x, r) = deep.right.split(spine_measure, pred);
-                (
-                    Self::deep_right(&deep.left, &deep.spine, l),
+                let (l, x, r) = deep.right.split(spine_measure, pred)?;
+                Ok((
+                    Self::deep_right(&deep.left, deep.spine(), l),
                     x.clone(),
                     Tree::from(r),
-                )
+                ))
             }
         }
     }
 
-    pub(crate) fn split_left<F>(
+    pub(crate) fn split_left<E, F>(
         &self,
         measure: V::Measure,
         pred: &mut F,
-    ) -> (Tree<R, V>, Node<R, V>)
+    ) -> Result<(Self, Node<R, V>), E>
     where
-        F: FnMut(&V::Measure) -> bool,
+        F: FnMut(&V::Measure) -> Result<bool, E>,
     {
         match self {
             Empty => unreachable!("recursive split of finger-tree called on empty tree"),
-            Single(value) => (Tree::empty(), value.clone()),
+            Single(value) => Ok((Tree::empty(), value.clone())),
             Deep(deep) => {
                 // left
                 let left_measure = measure.join(&deep.left.measure());
-                if pred(&left_measure) {
-                    let (l, x, _r) = deep.left.split(measure, pred);
-                    return (Tree::from(l), x.clone());
+                if pred(&left_measure)? {
+                    let (l, x, _r) = deep.left.split(measure, pred)?;
+                    return Ok((Tree::from(l), x.clone()));
                 }
                 // spine
-                let spine_measure = left_measure.join(&deep.spine.measure());
-                if pred(&spine_measure) {
-                    let (sl, sx) = deep.spine.split_left(left_measure.clone(), pred);
+                let spine_measure = left_measure.join(&deep.spine().measure());
+                if pred(&spine_measure)? {
+                    let (sl, sx) = deep.spine().split_left(left_measure.clone(), pred)?;
                     let sx = Digit::from(&sx);
-                    let (l, x, _r) = sx.split(left_measure.join(&sl.measure()), pred);
-                    return (Self::deep_right(&deep.left, &sl, l), x.clone());
+                    let (l, x, _r) = sx.split(left_measure.join(&sl.measure()), pred)?;
+                    return Ok((Self::deep_right(&deep.left, &sl, l), x.clone()));
                 }
                 // right
-                let (l, x, _r) = deep.right.split(spine_measure, pred);
-                (Self::deep_right(&deep.left, &deep.spine, l), x.clone())
+                let (l, x, _r) = deep.right.split(spine_measure, pred)?;
+                Ok((Self::deep_right(&deep.left, deep.spine(), l), x.clone()))
             }
         }
     }
 
-    pub(crate) fn split_right<F>(
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn split_right<E, F>(
         &self,
         measure: V::Measure,
         pred: &mut F,
-    ) -> (V::Measure, Node<R, V>, Tree<R, V>)
+    ) -> Result<(V::Measure, Node<R, V>, Self), E>
     where
-        F: FnMut(&V::Measure) -> bool,
+        F: FnMut(&V::Measure) -> Result<bool, E>,
     {
         match self {
             Empty => unreachable!("recursive split of finger-tree called on empty tree"),
-            Single(value) => (measure, value.clone(), Tree::empty()),
+            Single(value) => Ok((measure, value.clone(), Tree::empty())),
             Deep(deep) => {
                 // left
                 let left_measure = measure.join(&deep.left.measure());
-                if pred(&left_measure) {
-                    let (l, x, r) = deep.left.split(measure.to_owned(), pred);
-                    return (
+                if pred(&left_measure)? {
+                    let (l, x, r) = deep.left.split(measure.to_owned(), pred)?;
+                    return Ok((
                         measure.join(&l.measure()),
                         x.clone(),
-                        Self::deep_left(r, &deep.spine, &deep.right),
-                    );
+                        Self::deep_left(r, deep.spine(), &deep.right),
+                    ));
                 }
                 // spine
-                let spine_measure = left_measure.join(&deep.spine.measure());
-                if pred(&spine_measure) {
-                    let (slm, sx, sr) = deep.spine.split_right(left_measure.clone(), pred);
+                let spine_measure = left_measure.join(&deep.spine().measure());
+                if pred(&spine_measure)? {
+                    let (slm, sx, sr) = deep.spine().split_right(left_measure.clone(), pred)?;
                     let sx = Digit::from(&sx);
-                    let (l, x, r) = sx.split(slm.to_owned(), pred);
-                    return (
+                    let (l, x, r) = sx.split(slm.to_owned(), pred)?;
+                    return Ok((
                         slm.join(&l.measure()),
                         x.clone(),
                         Self::deep_left(r, &sr, &deep.right),
-                    );
+                    ));
                 }
                 // right
-                let (l, x, r) = deep.right.split(spine_measure.to_owned(), pred);
-                (spine_measure.join(&l.measure()), x.clone(), Tree::from(r))
+                let (l, x, r) = deep.right.split(spine_measure.to_owned(), pred)?;
+                Ok((spine_measure.join(&l.measure()), x.clone(), Tree::from(r)))
             }
         }
     }
 
-    fn push_left_many(self, iter: &mut dyn Iterator<Item = Node<R, V>>) -> Self {
-        match iter.next() {
-            None => self,
-            Some(node) => self.push_left_many(iter).push_left(node),
-        }
+    fn push_left_many(self, nodes: &NodeBuf<R, V>) -> Self {
+        nodes
+            .iter()
+            .rev()
+            .fold(self, |tree, node| tree.push_left(node.clone()))
     }
 
-    fn push_right_many(self, iter: &mut dyn Iterator<Item = Node<R, V>>) -> Self {
-        match iter.next() {
-            None => self,
-            Some(node) => self.push_right(node).push_right_many(iter),
-        }
+    fn push_right_many(self, nodes: &NodeBuf<R, V>) -> Self {
+        nodes
+            .iter()
+            .fold(self, |tree, node| tree.push_right(node.clone()))
     }
 
-    pub(crate) fn concat(
-        left: &Self,
-        mid: &mut dyn Iterator<Item = Node<R, V>>,
-        right: &Self,
-    ) -> Self {
+    /// Concatenate two trees with `mid` nodes inserted between them
+    pub(crate) fn concat(left: &Self, mid: &NodeBuf<R, V>, right: &Self) -> Self {
         match (left, right) {
             (Empty, _) => right.clone().push_left_many(mid),
             (_, Empty) => left.clone().push_right_many(mid),
             (Single(left), _) => right.clone().push_left_many(mid).push_left(left.clone()),
             (_, Single(right)) => left.clone().push_right_many(mid).push_right(right.clone()),
             (Deep(deep0), Deep(deep1)) => {
-                let left = deep0.right.as_ref().iter().cloned();
-                let right = deep1.left.as_ref().iter().cloned();
+                let (left, right) = (deep0.right.as_ref(), deep1.left.as_ref());
+                let mid = NodeBuf::lift(
+                    left.len() + mid.len() + right.len(),
+                    left.iter().chain(mid.iter()).chain(right),
+                );
                 Self::deep(
                     deep0.left.clone(),
-                    Self::concat(
-                        &deep0.spine,
-                        &mut Node::lift(left.chain(mid).chain(right)),
-                        &deep1.spine,
-                    ),
+                    Self::concat(deep0.spine(), &mid, deep1.spine()),
                     deep1.right.clone(),
                 )
             }
         }
     }
 
-    pub(crate) fn find<F>(&self, measure: V::Measure, pred: &mut F) -> &V
+    /// Find element for which predicate flips, returning it together with accumulated
+    /// measure of all elements before it
+    pub(crate) fn find<E, F>(
+        &self,
+        measure: V::Measure,
+        pred: &mut F,
+    ) -> Result<(V::Measure, &V), E>
     where
-        F: FnMut(&V::Measure) -> bool,
+        F: FnMut(&V::Measure) -> Result<bool, E>,
     {
         match self {
             Empty => unreachable!("recursive find of finger-tree called on empty tree"),
@@ -359,21 +523,415 @@ where
             Deep(deep) => {
                 // left
                 let left_measure = measure.join(&deep.left.measure());
-                if pred(&left_measure) {
-                    let (measure, node) = deep.left.find(measure, pred);
+                if pred(&left_measure)? {
+                    let (measure, node) = deep.left.find(measure, pred)?;
                     return node.find(measure, pred);
                 }
                 // spine
-                let spine_measure = left_measure.join(&deep.spine.measure());
-                if pred(&spine_measure) {
-                    return deep.spine.find(left_measure, pred);
+                let spine_measure = left_measure.join(&deep.spine().measure());
+                if pred(&spine_measure)? {
+                    return deep.spine().find(left_measure, pred);
                 }
                 // right
-                let (measure, node) = deep.right.find(spine_measure, pred);
+                let (measure, node) = deep.right.find(spine_measure, pred)?;
                 node.find(measure, pred)
             }
         }
     }
+
+    pub(crate) fn search<F>(&self, left: V::Measure, right: V::Measure, pred: &mut F) -> &V
+    where
+        F: FnMut(&V::Measure, &V::Measure) -> bool,
+    {
+        match self {
+            Empty => unreachable!("recursive search of finger-tree called on empty tree"),
+            Single(value) => value.search(left, right, pred),
+            Deep(deep) => {
+                let right_digit_measure = deep.right.measure().join(&right);
+                let spine_right_measure = deep.spine().measure().join(&right_digit_measure);
+                // left
+                let left_measure = left.join(&deep.left.measure());
+                if pred(&left_measure, &spine_right_measure) {
+                    let (left, right, node) = deep.left.search(left, spine_right_measure, pred);
+                    return node.search(left, right, pred);
+                }
+                // spine
+                let spine_measure = left_measure.join(&deep.spine().measure());
+                if pred(&spine_measure, &right_digit_measure) {
+                    return deep.spine().search(left_measure, right_digit_measure, pred);
+                }
+                // right
+                let (left, right, node) = deep.right.search(spine_measure, right, pred);
+                node.search(left, right, pred)
+            }
+        }
+    }
+
+    /// Construct tree of the same shape with `f` applied to all leafs
+    pub(crate) fn map<U, F>(&self, f: &mut F) -> Tree<R, U>
+    where
+        R: Refs<U>,
+        U: Measured,
+        F: FnMut(&V) -> U,
+    {
+        match self {
+            Empty => Tree::empty(),
+            Single(node) => Tree::single(node.map(f)),
+            Deep(deep) => Tree::deep(
+                deep.left.map(|node| node.map(f)),
+                deep.spine().map(f),
+                deep.right.map(|node| node.map(f)),
+            ),
+        }
+    }
+
+    /// Call `f` on all leaf nodes from left to right
+    pub(crate) fn for_each_leaf<F>(&self, f: &mut F)
+    where
+        F: FnMut(&Node<R, V>, &V),
+    {
+        let _ = self.try_fold_leaves((), false, &mut |(), node, value| {
+            f(node, value);
+            ControlFlow::<Infallible>::Continue(())
+        });
+    }
+
+    /// Fold all leaves of the tree from the left side, or from the right side
+    /// if `reverse` is set, stops as soon as `f` returns `ControlFlow::Break`
+    pub(crate) fn try_fold_leaves<A, B, F>(
+        &self,
+        acc: A,
+        reverse: bool,
+        f: &mut F,
+    ) -> ControlFlow<B, A>
+    where
+        F: FnMut(A, &Node<R, V>, &V) -> ControlFlow<B, A>,
+    {
+        match self {
+            Empty => ControlFlow::Continue(acc),
+            Single(node) => node.try_fold_leaves(acc, reverse, f),
+            Deep(deep) => {
+                let mut acc = acc;
+                if reverse {
+                    for node in deep.right.as_ref().iter().rev() {
+                        acc = node.try_fold_leaves(acc, reverse, f)?;
+                    }
+                    acc = deep.spine().try_fold_leaves(acc, reverse, f)?;
+                    for node in deep.left.as_ref().iter().rev() {
+                        acc = node.try_fold_leaves(acc, reverse, f)?;
+                    }
+                } else {
+                    for node in deep.left.as_ref() {
+                        acc = node.try_fold_leaves(acc, reverse, f)?;
+                    }
+                    acc = deep.spine().try_fold_leaves(acc, reverse, f)?;
+                    for node in deep.right.as_ref() {
+                        acc = node.try_fold_leaves(acc, reverse, f)?;
+                    }
+                }
+                ControlFlow::Continue(acc)
+            }
+        }
+    }
+
+    /// Check that all nodes of the tree have expected depth, that digits and nodes are
+    /// not wider than allowed, and that all cached measures are up to date, `depth` is
+    /// depth of nodes stored in digits of this tree
+    pub(crate) fn check(&self, depth: usize) -> Result<(), InvariantError<V::Measure>>
+    where
+        V::Measure: PartialEq,
+    {
+        match self {
+            Empty => Ok(()),
+            Single(node) => node.check(depth),
+            Deep(deep) => {
+                for digit in [&deep.left, &deep.right] {
+                    let size = digit.as_ref().len();
+                    if size > R::DIGIT_CAPACITY {
+                        return Err(InvariantError::DigitSize { depth, size });
+                    }
+                }
+                let mut computed = V::Measure::unit();
+                for node in deep.left.as_ref() {
+                    node.check(depth)?;
+                    computed = computed.join(&node.measure());
+                }
+                // lazy spine is checked without pushing its action down
+                let (action, spine) = deep.peek_spine();
+                spine.check(depth + 1)?;
+                computed = match action {
+                    Some(action) => computed.join(&action.act_measure(&spine.measure())),
+                    None => computed.join(&spine.measure()),
+                };
+                for node in deep.right.as_ref() {
+                    node.check(depth)?;
+                    computed = computed.join(&node.measure());
+                }
+                if deep.measure != computed {
+                    return Err(InvariantError::TreeMeasure {
+                        depth,
+                        cached: deep.measure.clone(),
+                        computed,
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Collect structural statistics, `depth` is depth of nodes stored in digits of this tree
+    pub(crate) fn stats(&self, depth: usize, stats: &mut Stats) {
+        let mut count = |node: &Node<R, V>, depth: usize| {
+            // lazy nodes are counted as the node they wrap
+            if node.is_lazy() {
+                return;
+            }
+            if stats.nodes.len() <= depth {
+                stats.nodes.resize(depth + 1, 0);
+            }
+            stats.nodes[depth] += 1;
+        };
+        match self {
+            Empty => (),
+            Single(node) => {
+                stats.depth = depth + 1;
+                node.visit(depth, &mut count);
+            }
+            Deep(deep) => {
+                stats.depth = depth + 1;
+                for digit in [&deep.left, &deep.right] {
+                    for node in digit.as_ref() {
+                        node.visit(depth, &mut count);
+                    }
+                    stats.digits[digit.as_ref().len() - 1] += 1;
+                }
+                deep.peek_spine().1.stats(depth + 1, stats);
+            }
+        }
+    }
+
+    /// Call `f` on all nodes of the tree together with their depth, `depth` is
+    /// depth of nodes stored in digits of this tree
+    pub(crate) fn visit<F>(&self, depth: usize, f: &mut F)
+    where
+        F: FnMut(&Node<R, V>, usize),
+    {
+        match self {
+            Empty => (),
+            Single(node) => node.visit(depth, f),
+            Deep(deep) => {
+                for node in deep.left.as_ref() {
+                    node.visit(depth, f);
+                }
+                deep.peek_spine().1.visit(depth + 1, f);
+                for node in deep.right.as_ref() {
+                    node.visit(depth, f);
+                }
+            }
+        }
+    }
+
+    /// Check that `pred` does not flip back to `false` on boundaries of digits on every
+    /// level of the tree and of nodes visited while descending to the element on which
+    /// it flips, `check` holds accumulated measure of all elements before this tree
+    pub(crate) fn check_split<F>(
+        &self,
+        mut check: SplitCheck<V::Measure>,
+        pred: &mut F,
+    ) -> Result<(), SplitError<V::Measure>>
+    where
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let mut found = None;
+        match self {
+            Empty => (),
+            Single(node) => {
+                if let Some(before) = check.step(&node.measure(), pred)? {
+                    found = Some((node, before));
+                }
+            }
+            Deep(deep) => {
+                for node in deep.left.as_ref() {
+                    if let Some(before) = check.step(&node.measure(), pred)? {
+                        found = Some((node, before));
+                    }
+                }
+                // spine descends on its own if predicate flips inside of it
+                deep.spine().check_split(check.clone(), pred)?;
+                check.step(&deep.spine().measure(), pred)?;
+                for node in deep.right.as_ref() {
+                    if let Some(before) = check.step(&node.measure(), pred)? {
+                        found = Some((node, before));
+                    }
+                }
+            }
+        }
+        match found {
+            None => Ok(()),
+            Some((node, before)) => node.check_split(&before, pred),
+        }
+    }
+
+    /// Find elements for all predicates from `preds` which flip inside of this tree,
+    /// `measure` is accumulated measure of all elements before this tree
+    pub(crate) fn find_many<'a, I, F>(
+        &'a self,
+        measure: &V::Measure,
+        preds: &mut Peekable<I>,
+        found: &mut Vec<Option<&'a V>>,
+    ) where
+        I: Iterator<Item = F>,
+        F: FnMut(&V::Measure) -> bool,
+    {
+        let next_measure = measure.join(&self.measure());
+        if !preds.peek_mut().is_some_and(|pred| pred(&next_measure)) {
+            return;
+        }
+        match self {
+            Empty => (),
+            Single(node) => node.find_many(measure, preds, found),
+            Deep(deep) => {
+                let mut measure = measure.clone();
+                for node in deep.left.as_ref() {
+                    node.find_many(&measure, preds, found);
+                    measure = measure.join(&node.measure());
+                }
+                deep.spine().find_many(&measure, preds, found);
+                measure = measure.join(&deep.spine().measure());
+                for node in deep.right.as_ref() {
+                    node.find_many(&measure, preds, found);
+                    measure = measure.join(&node.measure());
+                }
+            }
+        }
+    }
+
+    /// Call `piece` on all maximal subtrees of this tree which lie inside of the range
+    /// defined by `start` and `end` predicates, `measure` is accumulated measure of all
+    /// elements before this tree
+    pub(crate) fn range_pieces<'a, S, E, F>(
+        &'a self,
+        measure: &V::Measure,
+        start: &mut S,
+        end: &mut E,
+        piece: &mut F,
+    ) where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+        F: FnMut(RangePiece<'a, R, V>),
+    {
+        let next_measure = measure.join(&self.measure());
+        if !start(&next_measure) || end(measure) {
+            return;
+        }
+        if start(measure) && !end(&next_measure) {
+            return piece(RangePiece::Tree(self));
+        }
+        match self {
+            Empty => (),
+            Single(node) => node.range_pieces(measure, start, end, piece),
+            Deep(deep) => {
+                let mut measure = measure.clone();
+                for node in deep.left.as_ref() {
+                    node.range_pieces(&measure, start, end, piece);
+                    measure = measure.join(&node.measure());
+                }
+                deep.spine().range_pieces(&measure, start, end, piece);
+                measure = measure.join(&deep.spine().measure());
+                for node in deep.right.as_ref() {
+                    node.range_pieces(&measure, start, end, piece);
+                    measure = measure.join(&node.measure());
+                }
+            }
+        }
+    }
+
+    /// Measure of elements of this tree which lie inside of the range defined by `start`
+    /// and `end` predicates
+    pub(crate) fn measure_range<S, E>(&self, start: &mut S, end: &mut E) -> V::Measure
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+    {
+        let mut measure = V::Measure::unit();
+        self.range_pieces(&V::Measure::unit(), start, end, &mut |piece| {
+            measure = measure.join(&piece.measure())
+        });
+        measure
+    }
+
+    /// Apply action to all elements of the tree
+    ///
+    /// Action is applied to nodes of the digits and stored as a pending action of the
+    /// spine, so it takes constant time.
+    pub(crate) fn act(&self, action: &R::Action) -> Self {
+        match self {
+            Empty => Empty,
+            Single(node) => Single(node.act(action)),
+            Deep(deep) => Deep(R::Tree::new(TreeInner {
+                measure: action.act_measure(&deep.measure),
+                left: deep.left.map(|node| node.act(action)),
+                spine: deep.spine.act(action),
+                right: deep.right.map(|node| node.act(action)),
+            })),
+        }
+    }
+
+    /// Apply action to elements of the tree which lie inside of the range defined
+    /// by `start` and `end` predicates, `measure` is accumulated measure of all
+    /// elements before this tree
+    ///
+    /// Only nodes on the boundaries of the range are rebuilt, subtrees which lie
+    /// completely inside of the range get pending action.
+    pub(crate) fn act_range<S, E>(
+        &self,
+        measure: &V::Measure,
+        start: &mut S,
+        end: &mut E,
+        action: &R::Action,
+    ) -> Self
+    where
+        S: FnMut(&V::Measure) -> bool,
+        E: FnMut(&V::Measure) -> bool,
+    {
+        let next_measure = measure.join(&self.measure());
+        if !start(&next_measure) || end(measure) {
+            return self.clone();
+        }
+        if start(measure) && !end(&next_measure) {
+            return self.act(action);
+        }
+        match self {
+            Empty => Empty,
+            Single(node) => Single(node.act_range(measure, start, end, action)),
+            Deep(deep) => {
+                let mut measure = measure.clone();
+                let left = deep.left.map(|node| {
+                    let node_measure = measure.clone();
+                    measure = measure.join(&node.measure());
+                    node.act_range(&node_measure, start, end, action)
+                });
+                let spine = deep.spine().act_range(&measure, start, end, action);
+                measure = measure.join(&deep.spine().measure());
+                let right = deep.right.map(|node| {
+                    let node_measure = measure.clone();
+                    measure = measure.join(&node.measure());
+                    node.act_range(&node_measure, start, end, action)
+                });
+                Self::deep(left, spine, right)
+            }
+        }
+    }
+}
+
+impl<R, V> PushDown<R::Action> for Tree<R, V>
+where
+    R: Refs<V>,
+    V: Measured,
+{
+    fn push_down(&self, action: &R::Action) -> Self {
+        self.act(action)
+    }
 }
 
 impl<R, T, V> From<T> for Tree<R, V>
@@ -395,85 +953,31 @@ where
     R: Refs<V>,
     V: Measured,
 {
-    match nodes {
-        [] => Tree::empty(),
-        [n] => Tree::single(n.clone()),
-        [n0, n1] => Tree::deep(
-            Digit::One([n0.clone()]),
-            Tree::empty(),
-            Digit::One([n1.clone()]),
-        ),
-        [n0, n1, n2] => Tree::deep(
-            Digit::Two([n0.clone(), n1.clone()]),
-            Tree::empty(),
-            Digit::One([n2.clone()]),
-        ),
-        [n0, n1, n2, n3] => Tree::deep(
-            Digit::Two([n0.clone(), n1.clone()]),
-            Tree::empty(),
-            Digit::Two([n2.clone(), n3.clone()]),
-        ),
-        [n0, n1, n2, n3, n4] => Tree::deep(
-            Digit::Three([n0.clone(), n1.clone(), n2.clone()]),
-            Tree::empty(),
-            Digit::Two([n3.clone(), n4.clone()]),
-        ),
-        [n0, n1, n2, n3, n4, n5] => Tree::deep(
-            Digit::Three([n0.clone(), n1.clone(), n2.clone()]),
-            Tree::empty(),
-            Digit::Three([n3.clone(), n4.clone(), n5.clone()]),
-        ),
-        [n0, n1, n2, n3, n4, n5, n6] => Tree::deep(
-            Digit::Four([n0.clone(), n1.clone(), n2.clone(), n3.clone()]),
-            Tree::empty(),
-            Digit::Three([n4.clone(), n5.clone(), n6.clone()]),
-        ),
-        [n0, n1, n2, n3, n4, n5, n6, n7] => Tree::deep(
-            Digit::Four([n0.clone(), n1.clone(), n2.clone(), n3.clone()]),
-            Tree::empty(),
-            Digit::Four([n4.clone(), n5.clone(), n6.clone(), n7.clone()]),
-        ),
-        [n0, n1, n2, n3, n4, n5, n6, n7, n8] => Tree::deep(
-            Digit::Four([n0.clone(), n1.clone(), n2.clone(), n3.clone()]),
-            Tree::single(Node::node2(n4.clone(), n5.clone())),
-            Digit::Three([n6.clone(), n7.clone(), n8.clone()]),
-        ),
-        _ => {
-            let mut start = 4;
-            let end = nodes.len() - 4;
+    let capacity = R::DIGIT_CAPACITY;
+    match nodes.len() {
+        0 => Tree::empty(),
+        1 => Tree::single(nodes[0].clone()),
+        len if len <= 2 * capacity => {
+            let (left, right) = nodes.split_at(len.div_ceil(2));
+            Tree::deep(Digit::from(left), Tree::empty(), Digit::from(right))
+        }
+        len => {
+            let mut start = capacity;
+            let mut end = len - capacity;
+            if end - start == 1 {
+                // single node cannot be lifted, so right digit gives up one node
+                end += 1;
+            }
             let left = Digit::from(&nodes[..start]);
             let right = Digit::from(&nodes[end..]);
             // lift nodes in-place
             let mut offset = 0;
-            loop {
-                match end - start {
-                    0 => break,
-                    1 => unreachable!(),
-                    2 => {
-                        let node = Node::node2(nodes[start].clone(), nodes[start + 1].clone());
-                        nodes[offset] = node;
-                        start += 2;
-                        offset += 1;
-                    }
-                    4 => {
-                        let n0 = Node::node2(nodes[start].clone(), nodes[start + 1].clone());
-                        let n1 = Node::node2(nodes[start + 2].clone(), nodes[start + 3].clone());
-                        nodes[offset] = n0;
-                        nodes[offset + 1] = n1;
-                        start += 4;
-                        offset += 2;
-                    }
-                    _ => {
-                        let node = Node::node3(
-                            nodes[start].clone(),
-                            nodes[start + 1].clone(),
-                            nodes[start + 2].clone(),
-                        );
-                        nodes[offset] = node;
-                        start += 3;
-                        offset += 1;
-                    }
-                }
+            while start < end {
+                let size = node::lift_size(end - start, R::NODE_ARITY);
+                let node = Node::branch(&nodes[start..start + size]);
+                nodes[offset] = node;
+                start += size;
+                offset += 1;
             }
             Tree::deep(left, build(&mut nodes[..offset]), right)
         }